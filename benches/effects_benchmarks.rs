@@ -0,0 +1,69 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tarts::{
+    common::TerminalEffect, life, mandel, maze, rain::digital_rain, scope,
+};
+
+const SCREEN_SIZE: (u16, u16) = (80, 40);
+const SEED: Option<u64> = Some(42);
+
+/// One frame as run_loop does it, diff first then update
+fn frame<T: TerminalEffect>(effect: &mut T) {
+    black_box(effect.get_diff());
+    effect.update();
+}
+
+fn effects_frame_benchmark(c: &mut Criterion) {
+    c.bench_function("benchmark_matrix_frame", |b| {
+        let options = digital_rain::DigitalRainOptionsBuilder::default()
+            .screen_size(SCREEN_SIZE)
+            .drops_range((120, 240))
+            .speed_range((2, 16))
+            .seed(SEED)
+            .build()
+            .unwrap();
+        let mut effect = digital_rain::DigitalRain::new(options);
+        b.iter(|| frame(&mut effect))
+    });
+
+    c.bench_function("benchmark_life_frame", |b| {
+        let options = life::ConwayLifeOptionsBuilder::default()
+            .screen_size(SCREEN_SIZE)
+            .seed(SEED)
+            .build()
+            .unwrap();
+        let mut effect = life::ConwayLife::new(options);
+        b.iter(|| frame(&mut effect))
+    });
+
+    c.bench_function("benchmark_maze_frame", |b| {
+        let options = maze::MazeOptionsBuilder::default()
+            .screen_size(SCREEN_SIZE)
+            .seed(SEED)
+            .show_solution(true)
+            .build()
+            .unwrap();
+        let mut effect = maze::Maze::new(options);
+        b.iter(|| frame(&mut effect))
+    });
+
+    c.bench_function("benchmark_scope_frame", |b| {
+        let options = scope::ScopeOptionsBuilder::default()
+            .screen_size(SCREEN_SIZE)
+            .build()
+            .unwrap();
+        let mut effect = scope::Scope::new(options);
+        b.iter(|| frame(&mut effect))
+    });
+
+    c.bench_function("benchmark_mandel_frame", |b| {
+        let options = mandel::MandelOptionsBuilder::default()
+            .screen_size(SCREEN_SIZE)
+            .build()
+            .unwrap();
+        let mut effect = mandel::Mandel::new(options);
+        b.iter(|| frame(&mut effect))
+    });
+}
+
+criterion_group!(benches, effects_frame_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,347 @@
+//! Langton's Ant, ant turns depending on state of the cell it stands on,
+//! switches the cell to the next state and moves forward. With "RL" rules
+//! after about 10000 steps of chaos it starts building the "highway".
+//! Only a few cells change each frame, so diff is made from changed cells
+//! instead of comparing whole buffers.
+use crate::buffer::{resize_grid, Buffer, Cell};
+use crate::common::{make_rng, EffectRng, TerminalEffect};
+use crossterm::style;
+use derive_builder::Builder;
+use rand::Rng;
+
+/// Rules used when provided ones have less than two valid turns
+const DEFAULT_RULES: &str = "RL";
+
+/// Colors of the cell states, empty state is not drawn
+const STATE_COLORS: [style::Color; 6] = [
+    style::Color::Rgb {
+        r: 60,
+        g: 180,
+        b: 255,
+    },
+    style::Color::Rgb {
+        r: 255,
+        g: 200,
+        b: 60,
+    },
+    style::Color::Rgb {
+        r: 120,
+        g: 220,
+        b: 120,
+    },
+    style::Color::Rgb {
+        r: 220,
+        g: 100,
+        b: 220,
+    },
+    style::Color::Rgb {
+        r: 240,
+        g: 240,
+        b: 240,
+    },
+    style::Color::Rgb {
+        r: 255,
+        g: 110,
+        b: 90,
+    },
+];
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct AntOptions {
+    screen_size: (u16, u16),
+    /// Number of ants, first one starts at the center
+    #[builder(default = "1")]
+    ants: u16,
+    /// Turn for each cell state, 'R' or 'L', cell goes to the next state
+    /// after ant leaves it
+    #[builder(default = "DEFAULT_RULES.to_string()")]
+    rules: String,
+    #[builder(default = "20")]
+    steps_per_frame: u16,
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ant {
+    pub x: usize,
+    pub y: usize,
+    /// Heading clockwise from up: 0 up, 1 right, 2 down, 3 left
+    pub direction: u8,
+}
+
+pub struct Langton {
+    options: AntOptions,
+    buffer: Buffer,
+    /// State of every cell, index into rules
+    states: Vec<u8>,
+    /// Turn of each state, true for right
+    turns: Vec<bool>,
+    ants: Vec<Ant>,
+    /// Cells changed since last diff
+    dirty: Vec<(usize, usize)>,
+    rng: EffectRng,
+}
+
+impl Ant {
+    fn symbol(&self) -> char {
+        ['^', '>', 'v', '<'][self.direction as usize % 4]
+    }
+}
+
+impl TerminalEffect for Langton {
+    fn name(&self) -> &'static str {
+        "ant"
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        // ants are drawn over the cells they are standing on
+        for index in 0..self.ants.len() {
+            self.dirty.push((self.ants[index].x, self.ants[index].y));
+        }
+        self.dirty.sort_unstable_by_key(|&(x, y)| (y, x));
+        self.dirty.dedup();
+
+        let mut diff = vec![];
+        for (x, y) in self.dirty.drain(..) {
+            let cell = match self.ants.iter().find(|ant| (ant.x, ant.y) == (x, y)) {
+                Some(ant) => Cell::new(
+                    ant.symbol(),
+                    style::Color::Red,
+                    style::Attribute::Bold,
+                ),
+                None => state_cell(self.states[self.buffer.index_of(x, y)]),
+            };
+            if self.buffer.get(x, y) != cell {
+                self.buffer.set(x, y, cell);
+                diff.push((x, y, cell));
+            }
+        }
+        diff
+    }
+
+    fn update(&mut self) {
+        for _ in 0..self.options.steps_per_frame {
+            self.step();
+        }
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        let new_effect = Self::with_rng(self.options.clone(), self.rng.clone());
+        *self = new_effect;
+    }
+
+    /// Trail inside new screen is kept, ants outside are moved in
+    fn resize(&mut self, width: u16, height: u16) {
+        let from = self.buffer.get_size();
+        self.update_size(width, height);
+        let to = (width as usize, height as usize);
+        self.states = resize_grid(&self.states, from, to, 0);
+        for ant in self.ants.iter_mut() {
+            ant.x %= to.0;
+            ant.y %= to.1;
+        }
+        // screen is cleared, so every cell is redrawn
+        self.buffer = Buffer::new(to.0, to.1);
+        self.dirty = (0..to.1)
+            .flat_map(|y| (0..to.0).map(move |x| (x, y)))
+            .collect();
+    }
+}
+
+impl Langton {
+    /// Printed by `tarts ant --help`
+    pub fn help() -> &'static str {
+        "ant: Langton's ant, after chaos it starts building the highway
+
+Options:
+  --seed <n>        seed random generator
+"
+    }
+
+    pub fn new(options: AntOptions) -> Self {
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Create effect with provided random generator
+    pub fn with_rng(options: AntOptions, mut rng: EffectRng) -> Self {
+        let buffer = Buffer::new(
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        let (width, height) = buffer.get_size();
+
+        let mut ants = vec![];
+        for index in 0..options.ants.max(1) {
+            ants.push(match index {
+                0 => Ant {
+                    x: width / 2,
+                    y: height / 2,
+                    direction: 0,
+                },
+                _ => Ant {
+                    x: rng.gen_range(0..width),
+                    y: rng.gen_range(0..height),
+                    direction: rng.gen_range(0..4),
+                },
+            });
+        }
+
+        Self {
+            turns: parse_rules(&options.rules),
+            options,
+            states: vec![0; buffer.buffer.len()],
+            buffer,
+            ants,
+            dirty: vec![],
+            rng,
+        }
+    }
+
+    /// Move every ant one step, edges are wrapped around
+    pub fn step(&mut self) {
+        let (width, height) = self.buffer.get_size();
+        for ant in self.ants.iter_mut() {
+            let index = ant.y * width + ant.x;
+            let state = self.states[index] as usize;
+            ant.direction = match self.turns[state] {
+                true => (ant.direction + 1) % 4,
+                false => (ant.direction + 3) % 4,
+            };
+            self.states[index] = ((state + 1) % self.turns.len()) as u8;
+            self.dirty.push((ant.x, ant.y));
+
+            match ant.direction {
+                0 => ant.y = (ant.y + height - 1) % height,
+                1 => ant.x = (ant.x + 1) % width,
+                2 => ant.y = (ant.y + 1) % height,
+                _ => ant.x = (ant.x + width - 1) % width,
+            }
+        }
+    }
+}
+
+/// Turns from rules string, true for right, unknown characters are skipped
+pub fn parse_rules(rules: &str) -> Vec<bool> {
+    let parse = |rules: &str| -> Vec<bool> {
+        rules
+            .chars()
+            .filter_map(|turn| match turn.to_ascii_uppercase() {
+                'R' => Some(true),
+                'L' => Some(false),
+                _ => None,
+            })
+            .take(u8::MAX as usize)
+            .collect()
+    };
+    let turns = parse(rules);
+    match turns.len() {
+        0 | 1 => parse(DEFAULT_RULES),
+        _ => turns,
+    }
+}
+
+fn state_cell(state: u8) -> Cell {
+    match state {
+        0 => Cell::default(),
+        _ => Cell::new(
+            '█',
+            STATE_COLORS[(state as usize - 1) % STATE_COLORS.len()],
+            style::Attribute::Reset,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_ant(rules: &str) -> Langton {
+        let options = AntOptionsBuilder::default()
+            .screen_size((11, 11))
+            .rules(rules)
+            .steps_per_frame(1_u16)
+            .build()
+            .unwrap();
+        Langton::new(options)
+    }
+
+    #[test]
+    fn rules_parsed() {
+        assert_eq!(parse_rules("RLR"), vec![true, false, true]);
+        assert_eq!(parse_rules("r-l"), vec![true, false]);
+        assert_eq!(parse_rules("X"), vec![true, false]);
+    }
+
+    #[test]
+    fn ant_turns_flips_and_moves() {
+        let mut langton = single_ant("RL");
+        langton.step();
+        // empty cell turns ant right and gets flipped
+        assert_eq!(
+            langton.ants[0],
+            Ant {
+                x: 6,
+                y: 5,
+                direction: 1
+            }
+        );
+        assert_eq!(langton.states[langton.buffer.index_of(5, 5)], 1);
+
+        // four steps make a loop back to the start
+        for _ in 0..3 {
+            langton.step();
+        }
+        assert_eq!((langton.ants[0].x, langton.ants[0].y), (5, 5));
+        langton.step();
+        assert_eq!(langton.states[langton.buffer.index_of(5, 5)], 0);
+    }
+
+    #[test]
+    fn diff_of_changed_cells_only() {
+        let mut langton = single_ant("RL");
+        assert_eq!(langton.get_diff().len(), 1);
+        langton.update();
+        let diff = langton.get_diff();
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].2.symbol, '█');
+        assert_eq!(diff[1].2.symbol, '>');
+        assert!(langton.get_diff().is_empty());
+    }
+
+    #[test]
+    fn diff_matches_full_redraw() {
+        let options = AntOptionsBuilder::default()
+            .screen_size((20, 12))
+            .ants(3_u16)
+            .rules("RLR")
+            .seed(Some(8))
+            .build()
+            .unwrap();
+        let mut langton = Langton::new(options);
+        let mut screen = Buffer::new(20, 12);
+        for _ in 0..50 {
+            for (x, y, cell) in langton.get_diff() {
+                screen.set(x, y, cell);
+            }
+            langton.update();
+        }
+        for (x, y, cell) in langton.get_diff() {
+            screen.set(x, y, cell);
+        }
+        assert!(screen.diff(&langton.buffer).is_empty());
+        for (index, state) in langton.states.iter().enumerate() {
+            let (x, y) = screen.pos_of(index);
+            if !langton.ants.iter().any(|ant| (ant.x, ant.y) == (x, y)) {
+                assert_eq!(screen.get(x, y), state_cell(*state));
+            }
+        }
+    }
+}
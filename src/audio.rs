@@ -0,0 +1,110 @@
+//! Loudness of system audio used to drive effects in `--reactive` mode.
+//! Capture runs on audio thread and only stores the level, main loop reads
+//! it once per frame and passes to effect with `set_intensity`.
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+/// RMS of normal speech or music is far below 1.0, so it's amplified
+const GAIN: f32 = 4.0;
+
+/// Part of the previous level kept when sound gets quieter
+const DECAY: f32 = 0.9;
+
+/// Level in 0.0..=1.0 shared between audio and main threads, stored as
+/// bits of `f32` to be lock free
+#[derive(Debug, Clone, Default)]
+pub struct AudioLevel(Arc<AtomicU32>);
+
+impl AudioLevel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, level: f32) {
+        self.0
+            .store(level.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Update level from chunk of samples in -1.0..=1.0, rises at once
+    /// and falls slowly, so effects don't flicker between beats
+    pub fn feed(&self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let sum: f32 = samples.iter().map(|s| s * s).sum();
+        let rms = (sum / samples.len() as f32).sqrt();
+        let level = (rms * GAIN).min(1.0);
+        self.set(level.max(self.get() * DECAY));
+    }
+}
+
+/// Start capturing default input device, level is updated until returned
+/// stream is dropped
+#[cfg(feature = "audio")]
+pub fn capture(level: AudioLevel) -> Result<cpal::Stream, String> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or("No audio input device found")?;
+    let config = device.default_input_config().map_err(|e| e.to_string())?;
+    let on_error = |e| tracing::warn!("Audio stream error: {}", e);
+
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &config.into(),
+            move |data: &[f32], _: &cpal::InputCallbackInfo| level.feed(data),
+            on_error,
+            None,
+        ),
+        cpal::SampleFormat::I16 => device.build_input_stream(
+            &config.into(),
+            move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                let samples: Vec<f32> =
+                    data.iter().map(|s| *s as f32 / i16::MAX as f32).collect();
+                level.feed(&samples);
+            },
+            on_error,
+            None,
+        ),
+        format => return Err(format!("Unsupported sample format {}", format)),
+    }
+    .map_err(|e| e.to_string())?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loud_samples_raise_level() {
+        let level = AudioLevel::new();
+        level.feed(&[0.5, -0.5, 0.5, -0.5]);
+        assert_eq!(level.get(), 1.0);
+
+        let quiet = AudioLevel::new();
+        quiet.feed(&[0.05, -0.05]);
+        assert!(quiet.get() > 0.0 && quiet.get() < 0.5);
+    }
+
+    #[test]
+    fn level_decays_in_silence() {
+        let level = AudioLevel::new();
+        level.set(1.0);
+        level.feed(&[0.0; 16]);
+        assert!((level.get() - DECAY).abs() < f32::EPSILON);
+
+        // shared between clones, like between audio and main threads
+        let other = level.clone();
+        other.set(0.0);
+        assert_eq!(level.get(), 0.0);
+    }
+}
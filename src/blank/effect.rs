@@ -16,6 +16,10 @@ pub struct Blank {
 }
 
 impl TerminalEffect for Blank {
+    fn name(&self) -> &'static str {
+        "blank"
+    }
+
     fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
         let mut curr_buffer = Buffer::new(
             self.options.screen_size.0 as usize,
@@ -26,6 +30,7 @@ impl TerminalEffect for Blank {
             symbol: '#',
             color: style::Color::Green,
             attr: style::Attribute::Reset,
+            bg: style::Color::Reset,
         });
 
         let diff = self.buffer.diff(&curr_buffer);
@@ -45,6 +50,14 @@ impl TerminalEffect for Blank {
 }
 
 impl Blank {
+    /// Printed by `tarts blank --help`
+    pub fn help() -> &'static str {
+        "blank: empty screen, handy with --idle or --lock
+
+Has no options.
+"
+    }
+
     pub fn new(options: BlankOptions) -> Self {
         let mut buffer = Buffer::new(
             options.screen_size.0 as usize,
@@ -55,6 +68,7 @@ impl Blank {
             symbol: '#',
             color: style::Color::Green,
             attr: style::Attribute::Reset,
+            bg: style::Color::Reset,
         });
 
         Self { options, buffer }
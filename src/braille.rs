@@ -0,0 +1,164 @@
+use crate::buffer::{Buffer, Cell};
+use crossterm::style;
+
+/// Bits of braille dots, indexed by [y][x] inside of the cell
+pub const BRAILLE_DOTS: [[u8; 2]; 4] =
+    [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Grid of braille cells, each cell holds 2x4 dots, so it's possible
+/// to draw with double horizontal and quadruple vertical resolution
+#[derive(Clone)]
+pub struct BrailleCanvas {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<u8>,
+}
+
+impl BrailleCanvas {
+    /// Create canvas, width and height are in terminal cells
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![0; width * height],
+        }
+    }
+
+    /// Size of canvas in dots
+    #[inline]
+    pub fn dots_size(&self) -> (usize, usize) {
+        (self.width * 2, self.height * 4)
+    }
+
+    /// Raise dot, coordinates are in dots, outside of canvas ignored
+    pub fn set(&mut self, x: usize, y: usize) {
+        let (dots_width, dots_height) = self.dots_size();
+        if x < dots_width && y < dots_height {
+            self.cells[(y / 4) * self.width + x / 2] |= BRAILLE_DOTS[y % 4][x % 2];
+        }
+    }
+
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        let (dots_width, dots_height) = self.dots_size();
+        x < dots_width
+            && y < dots_height
+            && self.cells[(y / 4) * self.width + x / 2] & BRAILLE_DOTS[y % 4][x % 2]
+                != 0
+    }
+
+    /// Dots bitmask of cell
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.cells[y * self.width + x]
+    }
+
+    /// Bresenham line between two dots
+    pub fn line(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let (mut x0, mut y0) = (from.0 as isize, from.1 as isize);
+        let (x1, y1) = (to.0 as isize, to.1 as isize);
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            self.set(x0 as usize, y0 as usize);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    /// Draw non-empty cells into buffer with the same color
+    pub fn blit_to(&self, buffer: &mut Buffer, color: style::Color) {
+        self.blit_with(buffer, |_, _| color);
+    }
+
+    /// Draw non-empty cells into buffer, color picked for every cell
+    pub fn blit_with<F>(&self, buffer: &mut Buffer, mut color: F)
+    where
+        F: FnMut(usize, usize) -> style::Color,
+    {
+        let width = self.width.min(buffer.width);
+        let height = self.height.min(buffer.height);
+        for y in 0..height {
+            for x in 0..width {
+                let bits = self.get(x, y);
+                if bits != 0 {
+                    buffer.set(
+                        x,
+                        y,
+                        Cell::new(
+                            braille_char(bits),
+                            color(x, y),
+                            style::Attribute::Reset,
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Braille character with given dots raised
+#[inline]
+pub fn braille_char(bits: u8) -> char {
+    char::from_u32(0x2800 + bits as u32).unwrap_or(' ')
+}
+
+/// Dots bitmask of braille character, None for other characters
+pub fn braille_bits(symbol: char) -> Option<u8> {
+    match symbol as u32 {
+        code @ 0x2800..=0x28ff => Some((code - 0x2800) as u8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corner_dots() {
+        let mut canvas = BrailleCanvas::new(2, 2);
+        canvas.set(2, 4);
+        canvas.set(3, 4);
+        canvas.set(2, 7);
+        canvas.set(3, 7);
+        assert_eq!(canvas.get(1, 1), 0xC9);
+        assert_eq!(braille_char(canvas.get(1, 1)), '⣉');
+        assert_eq!(canvas.get(0, 0), 0);
+        assert!(canvas.is_set(3, 7));
+        assert!(!canvas.is_set(3, 6));
+    }
+
+    #[test]
+    fn out_of_canvas_ignored() {
+        let mut canvas = BrailleCanvas::new(2, 2);
+        canvas.set(4, 0);
+        canvas.set(0, 8);
+        assert!(canvas.cells.iter().all(|bits| *bits == 0));
+    }
+
+    #[test]
+    fn line_and_blit() {
+        let mut canvas = BrailleCanvas::new(3, 1);
+        canvas.line((0, 0), (5, 0));
+        let mut buffer = Buffer::new(3, 1);
+        canvas.blit_to(&mut buffer, style::Color::Green);
+        for x in 0..3 {
+            assert_eq!(buffer.get(x, 0).symbol, '⠉');
+            assert_eq!(buffer.get(x, 0).color, style::Color::Green);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Brian's Brain, automaton with three states of each cell:
+//!
+//! Firing:
+//!     Cell which is on becomes dying on the next generation.
+//! Dying:
+//!     Dying cell is off on the next generation.
+//! Birth:
+//!     Off cell turns on when exactly two of its neighbors are on.
+use crate::buffer::{resize_grid, Buffer, Cell};
+use crate::common::{make_rng, EffectRng, TerminalEffect};
+use crate::life::conway_life::get_neighbors_by_index_wrapped;
+use crossterm::style;
+use derive_builder::Builder;
+use rand::Rng;
+
+const ON_CHAR: char = '█';
+const DYING_CHAR: char = '▓';
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct BrainOptions {
+    screen_size: (u16, u16),
+    /// Part of cells turned on at start and when automaton dies out
+    #[builder(default = "0.2")]
+    density: f32,
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BrainState {
+    #[default]
+    Off,
+    On,
+    Dying,
+}
+
+pub struct Brain {
+    options: BrainOptions,
+    buffer: Buffer,
+    /// Next frame is drawn here and swapped with `buffer`
+    back_buffer: Buffer,
+    /// State of every cell, row by row like in buffer
+    states: Vec<BrainState>,
+    rng: EffectRng,
+}
+
+impl TerminalEffect for Brain {
+    fn name(&self) -> &'static str {
+        "brain"
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        self.back_buffer.clear();
+        fill_buffer(&self.states, &mut self.back_buffer);
+
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
+        diff
+    }
+
+    fn update(&mut self) {
+        self.states = self.next_generation();
+        if !self.states.contains(&BrainState::On) {
+            self.seed_cells();
+        }
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        let new_effect = Self::with_rng(self.options.clone(), self.rng.clone());
+        *self = new_effect;
+    }
+
+    /// States inside new screen are kept, new cells are off
+    fn resize(&mut self, width: u16, height: u16) {
+        let from = self.buffer.get_size();
+        self.update_size(width, height);
+        let to = (width as usize, height as usize);
+        self.states = resize_grid(&self.states, from, to, BrainState::Off);
+        self.buffer = Buffer::new(to.0, to.1);
+        self.back_buffer = self.buffer.clone();
+    }
+}
+
+impl Brain {
+    /// Printed by `tarts brain --help`
+    pub fn help() -> &'static str {
+        "brain: Brian's Brain automaton, firing cells leave dying trails
+
+Options:
+  --seed <n>        seed random generator
+"
+    }
+
+    pub fn new(options: BrainOptions) -> Self {
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Create effect with provided random generator
+    pub fn with_rng(options: BrainOptions, rng: EffectRng) -> Self {
+        let buffer = Buffer::new(
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        let mut brain = Self {
+            options,
+            back_buffer: buffer.clone(),
+            states: vec![BrainState::Off; buffer.buffer.len()],
+            buffer,
+            rng,
+        };
+        brain.seed_cells();
+        brain
+    }
+
+    /// Randomly turn on cells according to density
+    fn seed_cells(&mut self) {
+        let density = self.options.density.clamp(0.0, 1.0) as f64;
+        for state in self.states.iter_mut() {
+            if self.rng.gen_bool(density) {
+                *state = BrainState::On;
+            }
+        }
+    }
+
+    /// Apply rules to every cell, neighbors are wrapped around the edges
+    pub fn next_generation(&mut self) -> Vec<BrainState> {
+        fill_buffer(&self.states, &mut self.back_buffer);
+
+        self.states
+            .iter()
+            .enumerate()
+            .map(|(index, state)| match state {
+                BrainState::On => BrainState::Dying,
+                BrainState::Dying => BrainState::Off,
+                BrainState::Off => {
+                    let firing_neighbors =
+                        get_neighbors_by_index_wrapped(&self.back_buffer, index)
+                            .iter()
+                            .filter(|(_, cell)| cell.symbol == ON_CHAR)
+                            .count();
+                    match firing_neighbors {
+                        2 => BrainState::On,
+                        _ => BrainState::Off,
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Draw on cells white and dying ones blue, off cells are left blank
+pub fn fill_buffer(states: &[BrainState], buffer: &mut Buffer) {
+    for (index, state) in states.iter().enumerate() {
+        let cell = match state {
+            BrainState::On => {
+                Cell::new(ON_CHAR, style::Color::White, style::Attribute::Bold)
+            }
+            BrainState::Dying => {
+                Cell::new(DYING_CHAR, style::Color::Blue, style::Attribute::Reset)
+            }
+            BrainState::Off => Cell::default(),
+        };
+        buffer.buffer[index] = cell;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_brain(width: u16, height: u16) -> Brain {
+        let options = BrainOptionsBuilder::default()
+            .screen_size((width, height))
+            .density(0.0)
+            .build()
+            .unwrap();
+        Brain::new(options)
+    }
+
+    #[test]
+    fn cells_cycle_through_states() {
+        let mut brain = empty_brain(6, 6);
+        // two firing cells side by side give birth above and below them
+        brain.states[brain.buffer.index_of(2, 2)] = BrainState::On;
+        brain.states[brain.buffer.index_of(3, 2)] = BrainState::On;
+
+        let next = brain.next_generation();
+        let width = brain.buffer.width;
+        let index_of = |x, y| y * width + x;
+        assert_eq!(next[index_of(2, 2)], BrainState::Dying);
+        assert_eq!(next[index_of(3, 2)], BrainState::Dying);
+        for (x, y) in [(2, 1), (3, 1), (2, 3), (3, 3)] {
+            assert_eq!(next[index_of(x, y)], BrainState::On);
+        }
+        assert_eq!(next.iter().filter(|s| **s == BrainState::On).count(), 4);
+
+        brain.states = next;
+        let next = brain.next_generation();
+        assert_eq!(next[index_of(2, 2)], BrainState::Off);
+    }
+
+    #[test]
+    fn resize_keeps_overlapping_states() {
+        let mut brain = empty_brain(4, 3);
+        brain.states[brain.buffer.index_of(1, 1)] = BrainState::On;
+        brain.states[brain.buffer.index_of(3, 0)] = BrainState::Dying;
+
+        brain.resize(3, 5);
+        assert_eq!(brain.states.len(), 15);
+        assert_eq!(brain.states[brain.buffer.index_of(1, 1)], BrainState::On);
+        assert_eq!(
+            brain
+                .states
+                .iter()
+                .filter(|s| **s != BrainState::Off)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn reseeded_when_died_out() {
+        let options = BrainOptionsBuilder::default()
+            .screen_size((20, 10))
+            .seed(Some(2))
+            .build()
+            .unwrap();
+        let mut brain = Brain::new(options);
+        brain.states.fill(BrainState::Off);
+        brain.update();
+        assert!(brain.states.contains(&BrainState::On));
+    }
+
+    #[test]
+    fn same_seed_same_frames() {
+        let options = BrainOptionsBuilder::default()
+            .screen_size((30, 15))
+            .seed(Some(4))
+            .build()
+            .unwrap();
+        let mut first = Brain::new(options.clone());
+        let mut second = Brain::new(options);
+        for _ in 0..10 {
+            assert_eq!(first.get_diff(), second.get_diff());
+            first.update();
+            second.update();
+        }
+    }
+}
@@ -5,6 +5,8 @@ pub struct Cell {
     pub symbol: char,
     pub color: style::Color,
     pub attr: style::Attribute,
+    /// Background of the cell, `Reset` keeps one of the terminal
+    pub bg: style::Color,
 }
 
 /// Buffer implementation, coordinates unlike in crossterm started from [0, 0]
@@ -21,6 +23,7 @@ impl Cell {
             symbol,
             color,
             attr,
+            bg: style::Color::Reset,
         }
     }
 }
@@ -31,6 +34,7 @@ impl Default for Cell {
             symbol: ' ',
             color: style::Color::Black,
             attr: style::Attribute::Reset,
+            bg: style::Color::Reset,
         }
     }
 }
@@ -40,9 +44,11 @@ impl Buffer {
     // Indexing from 0: 0 1 2 3 4  | Square: 16
     // Indexing from 1: 1 2 3 4 5  | Square: 25
     // Need to check width of height are greater than zero
+    /// Empty buffer, size is clamped to at least 1x1 so zero sized
+    /// terminal doesn't break effects
     pub fn new(width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
         // fill buffer with dafault values
-        debug_assert!(width > 0 && height > 0);
         Self {
             width,
             height,
@@ -50,6 +56,46 @@ impl Buffer {
         }
     }
 
+    /// Buffer with lines of text starting from top left corner, width is
+    /// the longest line counted in chars, spaces are left default
+    pub fn from_text(
+        lines: &[&str],
+        color: style::Color,
+        attr: style::Attribute,
+    ) -> Self {
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut buffer = Buffer::new(width, lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            for (x, symbol) in line.chars().enumerate() {
+                if symbol != ' ' {
+                    buffer.set(x, y, Cell::new(symbol, color, attr));
+                }
+            }
+        }
+        buffer
+    }
+
+    /// Reset all cells to default, keeps allocated memory
+    pub fn clear(&mut self) {
+        self.fill_with(&Cell::default());
+    }
+
+    /// Change size keeping cells which fit into new one, added cells are
+    /// default, size is clamped like in `new`
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let mut resized = Buffer::new(width, height);
+        for y in 0..self.height.min(resized.height) {
+            for x in 0..self.width.min(resized.width) {
+                resized.set(x, y, self.get(x, y));
+            }
+        }
+        *self = resized;
+    }
+
     pub fn fill_with(&mut self, cell: &Cell) {
         self.buffer.fill(*cell);
     }
@@ -80,7 +126,8 @@ impl Buffer {
         (i % self.width, i / self.width)
     }
 
-    // Return x, y and Cell
+    /// Return x, y and Cell of changed cells, sorted by row and then by
+    /// column, so renderer moves cursor forward only
     pub fn diff(&self, other: &Buffer) -> Vec<(usize, usize, Cell)> {
         let prev_buffer = &self.buffer;
         let next_buffer = &other.buffer;
@@ -100,12 +147,28 @@ impl Buffer {
         updates
     }
 
-    #[allow(dead_code)]
-    pub fn iter(&self) -> std::slice::Iter<Cell> {
+    pub fn iter(&self) -> std::slice::Iter<'_, Cell> {
         self.buffer.iter()
     }
 }
 
+/// Copy row by row grid of size `from` into one of size `to`, same as
+/// `Buffer::resize` does with cells, new places get `fill`
+pub fn resize_grid<T: Clone>(
+    grid: &[T],
+    from: (usize, usize),
+    to: (usize, usize),
+    fill: T,
+) -> Vec<T> {
+    let mut resized = vec![fill; to.0 * to.1];
+    for y in 0..from.1.min(to.1) {
+        for x in 0..from.0.min(to.0) {
+            resized[y * to.0 + x] = grid[y * from.0 + x].clone();
+        }
+    }
+    resized
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,4 +210,93 @@ mod tests {
         let diff = buf.diff(&next_buf);
         assert_eq!(diff.len(), 3);
     }
+
+    #[test]
+    fn diff_includes_origin() {
+        let buf = Buffer::new(3, 3);
+        let mut next_buf = Buffer::new(3, 3);
+        let cell =
+            Cell::new('a', style::Color::Green, style::Attribute::NormalIntensity);
+        next_buf.set(0, 0, cell);
+
+        let diff = buf.diff(&next_buf);
+        assert_eq!(diff, vec![(0, 0, cell)]);
+    }
+
+    #[test]
+    fn diff_sorted_by_row_and_column() {
+        let buf = Buffer::new(5, 4);
+        let mut next_buf = Buffer::new(5, 4);
+        let cell =
+            Cell::new('a', style::Color::Green, style::Attribute::NormalIntensity);
+        for (x, y) in [(4, 3), (0, 2), (3, 0), (1, 2), (0, 0), (4, 1)] {
+            next_buf.set(x, y, cell);
+        }
+
+        let positions: Vec<(usize, usize)> = buf
+            .diff(&next_buf)
+            .iter()
+            .map(|(x, y, _)| (*y, *x))
+            .collect();
+        let mut sorted = positions.clone();
+        sorted.sort();
+        assert_eq!(positions.len(), 6);
+        assert_eq!(positions, sorted);
+    }
+
+    #[test]
+    fn resize_keeps_overlapping_cells() {
+        let mut buf = Buffer::new(3, 2);
+        let cell =
+            Cell::new('a', style::Color::Green, style::Attribute::NormalIntensity);
+        buf.set(1, 1, cell);
+        buf.set(2, 0, cell);
+
+        buf.resize(2, 4);
+        assert_eq!(buf.get_size(), (2, 4));
+        assert_eq!(buf.buffer.len(), 8);
+        assert_eq!(buf.get(1, 1), cell);
+        assert_eq!(buf.get(1, 0), Cell::default());
+        assert_eq!(buf.get(1, 3), Cell::default());
+    }
+
+    #[test]
+    fn zero_size_clamped() {
+        let buf = Buffer::new(0, 5);
+        assert_eq!((buf.width, buf.height), (1, 5));
+        assert_eq!(buf.buffer.len(), 5);
+
+        let buf = Buffer::new(0, 0);
+        assert_eq!(buf.buffer.len(), 1);
+    }
+
+    #[test]
+    fn clear_keeps_size() {
+        let mut buf = Buffer::new(4, 3);
+        buf.fill_with(&Cell::new('x', style::Color::Green, style::Attribute::Bold));
+        buf.clear();
+        assert_eq!(buf.get_size(), (4, 3));
+        assert_eq!(buf.buffer.len(), 12);
+        assert!(buf.iter().all(|cell| *cell == Cell::default()));
+    }
+
+    #[test]
+    fn from_text_sized_by_longest_line() {
+        let buf = Buffer::from_text(
+            &["ab", "", "éèç d"],
+            style::Color::Green,
+            style::Attribute::Bold,
+        );
+        // multi-byte chars take single cell each
+        assert_eq!(buf.get_size(), (5, 3));
+        assert_eq!(buf.get(0, 0).symbol, 'a');
+        assert_eq!(buf.get(2, 2).symbol, 'ç');
+        assert_eq!(buf.get(4, 2).color, style::Color::Green);
+        assert_eq!(buf.get(3, 2), Cell::default());
+        assert_eq!(buf.get(1, 1), Cell::default());
+
+        let buf =
+            Buffer::from_text(&[], style::Color::Green, style::Attribute::Bold);
+        assert_eq!(buf.get_size(), (1, 1));
+    }
 }
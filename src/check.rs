@@ -1,3 +1,4 @@
+use crate::buffer::Buffer;
 use crate::common::TerminalEffect;
 use crossterm::{
     cursor,
@@ -60,42 +61,233 @@ pub fn check<T: TerminalEffect>(effect: &mut T, frames: usize) -> io::Result<()>
     Ok(())
 }
 
-pub fn run_check(effect_name: &str, frames: usize) -> io::Result<()> {
-    match effect_name {
+/// Run effect without terminal, applying diffs of each frame onto a buffer
+/// of given size, returns state of the screen after every frame
+pub fn capture_frames<T: TerminalEffect>(
+    effect: &mut T,
+    screen_size: (u16, u16),
+    frames: usize,
+) -> Vec<Buffer> {
+    let mut screen = Buffer::new(screen_size.0 as usize, screen_size.1 as usize);
+    let mut captured = Vec::with_capacity(frames);
+
+    for _ in 0..frames {
+        for (x, y, cell) in effect.get_diff() {
+            screen.set(x, y, cell);
+        }
+        captured.push(screen.clone());
+        effect.update();
+    }
+
+    captured
+}
+
+/// Effect with default options, as `tarts check` runs it
+pub fn default_effect(
+    effect_name: &str,
+    size: (u16, u16),
+    seed: Option<u64>,
+) -> Option<Box<dyn TerminalEffect>> {
+    let effect: Box<dyn TerminalEffect> = match effect_name {
         "matrix" => {
             let options =
                 crate::rain::digital_rain::DigitalRainOptionsBuilder::default()
-                    .screen_size(terminal::size()?)
+                    .screen_size(size)
                     .drops_range((120, 240))
                     .speed_range((2, 16))
+                    .seed(seed)
                     .build()
                     .unwrap();
-            let mut digital_rain =
-                crate::rain::digital_rain::DigitalRain::new(options);
-            check(&mut digital_rain, frames)
+            Box::new(crate::rain::digital_rain::DigitalRain::new(options))
         }
         "life" => {
             let options = crate::life::ConwayLifeOptionsBuilder::default()
-                .screen_size(terminal::size()?)
+                .screen_size(size)
+                .seed(seed)
                 .build()
                 .unwrap();
-            let mut conway_life = crate::life::ConwayLife::new(options);
-            check(&mut conway_life, frames)
+            Box::new(crate::life::ConwayLife::new(options))
         }
         "maze" => {
             let options = crate::maze::MazeOptionsBuilder::default()
-                .screen_size(terminal::size()?)
+                .screen_size(size)
+                .seed(seed)
+                .show_solution(true)
+                .build()
+                .unwrap();
+            Box::new(crate::maze::Maze::new(options))
+        }
+        "scope" => {
+            let options = crate::scope::ScopeOptionsBuilder::default()
+                .screen_size(size)
+                .build()
+                .unwrap();
+            Box::new(crate::scope::Scope::new(options))
+        }
+        "brain" => {
+            let options = crate::brain::BrainOptionsBuilder::default()
+                .screen_size(size)
+                .seed(seed)
+                .build()
+                .unwrap();
+            Box::new(crate::brain::Brain::new(options))
+        }
+        "wator" => {
+            let options = crate::wator::WatorOptionsBuilder::default()
+                .screen_size(size)
+                .seed(seed)
+                .build()
+                .unwrap();
+            Box::new(crate::wator::Wator::new(options))
+        }
+        "sand" => {
+            let options = crate::sand::SandOptionsBuilder::default()
+                .screen_size(size)
+                .seed(seed)
+                .build()
+                .unwrap();
+            Box::new(crate::sand::Sand::new(options))
+        }
+        "ant" => {
+            let options = crate::ant::AntOptionsBuilder::default()
+                .screen_size(size)
+                .seed(seed)
+                .build()
+                .unwrap();
+            Box::new(crate::ant::Langton::new(options))
+        }
+        "eca" => {
+            let options = crate::eca::EcaOptionsBuilder::default()
+                .screen_size(size)
+                .seed(seed)
+                .build()
+                .unwrap();
+            Box::new(crate::eca::Eca::new(options))
+        }
+        "mandel" => {
+            let options = crate::mandel::MandelOptionsBuilder::default()
+                .screen_size(size)
                 .build()
                 .unwrap();
-            let mut maze = crate::maze::Maze::new(options);
-            check(&mut maze, frames)
+            Box::new(crate::mandel::Mandel::new(options))
         }
-        _ => {
+        "globe" => {
+            let options = crate::globe::GlobeOptionsBuilder::default()
+                .screen_size(size)
+                .build()
+                .unwrap();
+            Box::new(crate::globe::Globe::new(options))
+        }
+        "clock" => {
+            let options = crate::clock::ClockOptionsBuilder::default()
+                .screen_size(size)
+                .seed(seed)
+                .build()
+                .unwrap();
+            Box::new(crate::clock::Clock::new(options))
+        }
+        "blank" => {
+            let options = crate::blank::BlankOptionsBuilder::default()
+                .screen_size(size)
+                .build()
+                .unwrap();
+            Box::new(crate::blank::Blank::new(options))
+        }
+        _ => return None,
+    };
+    Some(effect)
+}
+
+pub fn run_check(
+    effect_name: &str,
+    frames: usize,
+    seed: Option<u64>,
+) -> io::Result<()> {
+    let (width, height) = terminal::size()?;
+    if width == 0 || height == 0 {
+        println!("Terminal size is {}x{}, nothing to draw on", width, height);
+        return Ok(());
+    }
+
+    match default_effect(effect_name, (width, height), seed) {
+        Some(mut effect) => check(&mut effect, frames),
+        None => {
             println!(
-                "Unknown effect: {}. Available effects are: matrix, life, maze",
-                effect_name
+                "Unknown effect: {}. Available effects are: {}",
+                effect_name,
+                crate::common::VALID_SAVERS.join(", ")
             );
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Cell;
+    use crate::common::buffer_to_string;
+    use crossterm::style;
+
+    #[test]
+    fn buffer_as_text_grid() {
+        let mut buffer = Buffer::new(3, 2);
+        let cell = Cell::new('#', style::Color::Green, style::Attribute::Reset);
+        buffer.set(0, 0, cell);
+        buffer.set(2, 1, cell);
+        assert_eq!(buffer_to_string(&buffer), "#  \n  #");
+    }
+
+    #[test]
+    fn every_effect_runs_at_min_size() {
+        // effects which don't override it get 1x1 screen
+        for name in crate::common::VALID_SAVERS {
+            let size = default_effect(name, (80, 24), Some(1)).unwrap().min_size();
+            let mut effect = default_effect(name, size, Some(1)).unwrap();
+            for _ in 0..10 {
+                for (x, y, _) in effect.get_diff() {
+                    assert!(x < size.0 as usize && y < size.1 as usize, "{}", name);
+                }
+                effect.update();
+            }
+        }
+    }
+
+    #[test]
+    fn capture_frames_headless() {
+        let options = crate::life::ConwayLifeOptionsBuilder::default()
+            .screen_size((20, 10))
+            .seed(Some(1))
+            .build()
+            .unwrap();
+        let mut life = crate::life::ConwayLife::new(options.clone());
+        let frames = capture_frames(&mut life, (20, 10), 5);
+        assert_eq!(frames.len(), 5);
+
+        // same seed gives the same frames
+        let mut life = crate::life::ConwayLife::new(options);
+        let again = capture_frames(&mut life, (20, 10), 5);
+        for (first, second) in frames.iter().zip(again.iter()) {
+            assert_eq!(buffer_to_string(first), buffer_to_string(second));
+        }
+    }
+
+    #[test]
+    fn scope_snapshot() {
+        let options = crate::scope::ScopeOptionsBuilder::default()
+            .screen_size((16, 5))
+            .build()
+            .unwrap();
+        let mut scope = crate::scope::Scope::new(options);
+        let frames = capture_frames(&mut scope, (16, 5), 1);
+        let expected = [
+            "⢾⡉⠉⠉⣉⡹⠶⠖⠲⠶⢏⣉⠉⠉⢉⡷",
+            "⢀⡼⠛⠯⣄⡀    ⢀⣠⠽⠛⢧⡀",
+            "⣏    ⢈⡱⠶⠶⢎⡁    ⣹",
+            "⠈⢳⣤⣖⠋⠁    ⠈⠙⣲⣤⡞⠁",
+            "⢾⣁⣀⣀⣉⣱⠶⠦⠴⠶⣎⣉⣀⣀⣈⡷",
+        ]
+        .join("\n");
+        assert_eq!(buffer_to_string(&frames[0]), expected);
+    }
+}
@@ -0,0 +1,324 @@
+//! Current time drawn with big block digits, clock jumps to a random place
+//! every minute so it doesn't burn into the screen.
+use crate::buffer::{Buffer, Cell};
+use crate::common::{make_rng, EffectRng, TerminalEffect};
+use chrono::{Local, Timelike};
+use crossterm::style;
+use derive_builder::Builder;
+use rand::Rng;
+
+/// 5x7 glyphs of digits, `#` is a lit pixel
+const DIGITS: [[&str; 7]; 10] = [
+    [
+        " ### ", "#   #", "#  ##", "# # #", "##  #", "#   #", " ### ",
+    ],
+    [
+        "  #  ", " ##  ", "  #  ", "  #  ", "  #  ", "  #  ", " ### ",
+    ],
+    [
+        " ### ", "#   #", "    #", "   # ", "  #  ", " #   ", "#####",
+    ],
+    [
+        "#####", "   # ", "  #  ", "   # ", "    #", "#   #", " ### ",
+    ],
+    [
+        "   # ", "  ## ", " # # ", "#  # ", "#####", "   # ", "   # ",
+    ],
+    [
+        "#####", "#    ", "#### ", "    #", "    #", "#   #", " ### ",
+    ],
+    [
+        "  ## ", " #   ", "#    ", "#### ", "#   #", "#   #", " ### ",
+    ],
+    [
+        "#####", "    #", "   # ", "  #  ", " #   ", " #   ", " #   ",
+    ],
+    [
+        " ### ", "#   #", "#   #", " ### ", "#   #", "#   #", " ### ",
+    ],
+    [
+        " ### ", "#   #", "#   #", " ####", "    #", "   # ", " ##  ",
+    ],
+];
+
+/// Colon between hours, minutes and seconds
+const COLON: [&str; 7] = ["   ", " # ", " # ", "   ", " # ", " # ", "   "];
+
+const GLYPH_HEIGHT: usize = 7;
+
+/// Empty columns between glyphs
+const SPACING: usize = 1;
+
+const DIGITS_COLOR: style::Color = style::Color::Rgb {
+    r: 0,
+    g: 200,
+    b: 255,
+};
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct ClockOptions {
+    screen_size: (u16, u16),
+    /// Show hours 1..12 with AM or PM under the digits instead of 0..23
+    #[builder(default)]
+    twelve_hour: bool,
+    #[builder(default = "true")]
+    show_seconds: bool,
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    seed: Option<u64>,
+}
+
+pub struct Clock {
+    options: ClockOptions,
+    buffer: Buffer,
+    /// Next frame is drawn here and swapped with `buffer`
+    back_buffer: Buffer,
+    rng: EffectRng,
+    /// Hours, minutes and seconds shown
+    time: (u32, u32, u32),
+    /// Top left corner of the digits
+    position: (usize, usize),
+}
+
+impl TerminalEffect for Clock {
+    fn name(&self) -> &'static str {
+        "clock"
+    }
+
+    fn min_size(&self) -> (u16, u16) {
+        (text_width(&self.text()) as u16, GLYPH_HEIGHT as u16 + 1)
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        self.back_buffer.clear();
+        self.fill_buffer();
+
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
+        diff
+    }
+
+    fn update(&mut self) {
+        let now = Local::now();
+        self.set_time(now.hour(), now.minute(), now.second());
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        let new_effect = Self::with_rng(self.options.clone(), self.rng.clone());
+        *self = new_effect;
+    }
+
+    /// Clock stays in place unless it doesn't fit anymore
+    fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        self.buffer = Buffer::new(width as usize, height as usize);
+        self.back_buffer = self.buffer.clone();
+        let (x, y) = self.position;
+        if x + text_width(&self.text()) > self.buffer.width
+            || y + GLYPH_HEIGHT + 1 > self.buffer.height
+        {
+            self.move_randomly();
+        }
+    }
+}
+
+impl Clock {
+    /// Printed by `tarts clock --help`
+    pub fn help() -> &'static str {
+        "clock: current time with big digits, jumps to a random place every
+minute
+
+Options:
+  --12h             hours 1..12 with AM or PM, 0..23 by default
+  --no-seconds      show only hours and minutes
+  --seed <n>        seed random generator
+"
+    }
+
+    pub fn new(options: ClockOptions) -> Self {
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Create effect with provided random generator
+    pub fn with_rng(options: ClockOptions, rng: EffectRng) -> Self {
+        let buffer = Buffer::new(
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        let now = Local::now();
+        let mut clock = Self {
+            options,
+            back_buffer: buffer.clone(),
+            buffer,
+            rng,
+            time: (now.hour(), now.minute(), now.second()),
+            position: (0, 0),
+        };
+        clock.move_randomly();
+        clock
+    }
+
+    /// Show given time, clock is moved when minute changes
+    pub fn set_time(&mut self, hour: u32, minute: u32, second: u32) {
+        let minute_changed = minute != self.time.1;
+        self.time = (hour, minute, second);
+        if minute_changed {
+            self.move_randomly();
+        }
+    }
+
+    /// Time as drawn on screen, like `09:41:07`
+    pub fn text(&self) -> String {
+        let (hour, minute, second) = self.time;
+        let hour = match self.options.twelve_hour {
+            true if hour % 12 == 0 => 12,
+            true => hour % 12,
+            false => hour,
+        };
+        match self.options.show_seconds {
+            true => format!("{:02}:{:02}:{:02}", hour, minute, second),
+            false => format!("{:02}:{:02}", hour, minute),
+        }
+    }
+
+    /// Pick new place where whole clock fits, top left if screen is too small
+    fn move_randomly(&mut self) {
+        let width = text_width(&self.text());
+        let free_x = self.buffer.width.saturating_sub(width);
+        let free_y = self.buffer.height.saturating_sub(GLYPH_HEIGHT + 1);
+        self.position = (
+            self.rng.gen_range(0..=free_x),
+            self.rng.gen_range(0..=free_y),
+        );
+    }
+
+    fn fill_buffer(&mut self) {
+        let (mut x, y) = self.position;
+        let text = self.text();
+        for ch in text.chars() {
+            let glyph: &[&str; 7] = match ch.to_digit(10) {
+                Some(digit) => &DIGITS[digit as usize],
+                None => &COLON,
+            };
+            for (dy, row) in glyph.iter().enumerate() {
+                for (dx, pixel) in row.chars().enumerate() {
+                    if pixel == '#' {
+                        self.set(x + dx, y + dy, '█');
+                    }
+                }
+            }
+            x += glyph[0].len() + SPACING;
+        }
+
+        if self.options.twelve_hour {
+            let suffix = if self.time.0 < 12 { "AM" } else { "PM" };
+            let right = self.position.0 + text_width(&text);
+            for (i, ch) in suffix.chars().enumerate() {
+                self.set(right - suffix.len() + i, y + GLYPH_HEIGHT, ch);
+            }
+        }
+    }
+
+    /// Draw cell of the clock, parts outside of the screen are cut
+    fn set(&mut self, x: usize, y: usize, symbol: char) {
+        if x < self.back_buffer.width && y < self.back_buffer.height {
+            self.back_buffer.set(
+                x,
+                y,
+                Cell::new(symbol, DIGITS_COLOR, style::Attribute::Bold),
+            );
+        }
+    }
+}
+
+/// Number of columns text takes when drawn with big glyphs
+fn text_width(text: &str) -> usize {
+    let glyphs: usize = text
+        .chars()
+        .map(|ch| match ch.is_ascii_digit() {
+            true => DIGITS[0][0].len(),
+            false => COLON[0].len(),
+        })
+        .sum();
+    glyphs + SPACING * text.chars().count().saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_clock(twelve_hour: bool, show_seconds: bool) -> Clock {
+        let options = ClockOptionsBuilder::default()
+            .screen_size((80, 24))
+            .twelve_hour(twelve_hour)
+            .show_seconds(show_seconds)
+            .seed(Some(42))
+            .build()
+            .unwrap();
+        Clock::new(options)
+    }
+
+    #[test]
+    fn time_formats() {
+        let mut clock = get_clock(false, true);
+        clock.set_time(0, 5, 9);
+        assert_eq!(clock.text(), "00:05:09");
+        assert_eq!(text_width(&clock.text()), 6 * 5 + 2 * 3 + 7);
+
+        let mut clock = get_clock(true, false);
+        clock.set_time(0, 5, 9);
+        assert_eq!(clock.text(), "12:05");
+        clock.set_time(15, 30, 0);
+        assert_eq!(clock.text(), "03:30");
+    }
+
+    #[test]
+    fn digits_drawn_in_block_font() {
+        let mut clock = get_clock(false, false);
+        clock.set_time(11, 11, 0);
+        clock.position = (0, 0);
+        clock.get_diff();
+
+        // stem of the first "1" goes through the whole height
+        for y in 0..GLYPH_HEIGHT {
+            assert_eq!(clock.buffer.get(2, y).symbol, '█');
+        }
+        assert_eq!(clock.buffer.get(0, 3), Cell::default());
+        assert_eq!(clock.buffer.get(13, 1).symbol, '█');
+    }
+
+    #[test]
+    fn moves_every_minute() {
+        let mut clock = get_clock(false, true);
+        clock.set_time(10, 0, 0);
+        let position = clock.position;
+        for second in 1..60 {
+            clock.set_time(10, 0, second);
+            assert_eq!(clock.position, position);
+        }
+
+        let mut moved = false;
+        for minute in 1..10 {
+            clock.set_time(10, minute, 0);
+            moved |= clock.position != position;
+        }
+        assert!(moved);
+    }
+
+    #[test]
+    fn am_pm_under_digits() {
+        let mut clock = get_clock(true, false);
+        clock.set_time(15, 0, 0);
+        clock.position = (0, 0);
+        clock.get_diff();
+        let width = text_width(&clock.text());
+        assert_eq!(clock.buffer.get(width - 2, GLYPH_HEIGHT).symbol, 'P');
+        assert_eq!(clock.buffer.get(width - 1, GLYPH_HEIGHT).symbol, 'M');
+    }
+}
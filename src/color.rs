@@ -0,0 +1,331 @@
+//! Down-conversion of rgb colors for terminals without truecolor support
+//! and parsing of colors given by user
+use crossterm::style::Color;
+use std::{env, str::FromStr};
+
+/// Levels of each channel in 6x6x6 cube of 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Basic 16 colors with their usual (xterm) rgb values
+const ANSI_16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// How many colors terminal is able to show
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    #[default]
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "true" => Ok(ColorMode::TrueColor),
+            "256" => Ok(ColorMode::Ansi256),
+            "16" => Ok(ColorMode::Ansi16),
+            _ => Err(format!("unknown color mode {}, use true, 256 or 16", s)),
+        }
+    }
+}
+
+/// Parse color given as `#RRGGBB`, `rgb(r, g, b)` or crossterm name like
+/// `green` or `dark_grey`
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    let s = s.trim();
+    let invalid =
+        || format!("invalid color {}, use #RRGGBB, rgb(r,g,b) or name", s);
+
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+        return match (channel(0), channel(2), channel(4)) {
+            (Ok(r), Ok(g), Ok(b)) => Ok(Color::Rgb { r, g, b }),
+            _ => Err(invalid()),
+        };
+    }
+
+    if let Some(args) = s.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+        let channels: Vec<u8> = args
+            .split(',')
+            .map(|c| c.trim().parse::<u8>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        return match channels[..] {
+            [r, g, b] => Ok(Color::Rgb { r, g, b }),
+            _ => Err(invalid()),
+        };
+    }
+
+    Color::try_from(s).map_err(|_| invalid())
+}
+
+/// Guess color support from `COLORTERM` and `TERM` environment variables
+pub fn detect_color_support() -> ColorMode {
+    color_support_from(
+        env::var("COLORTERM").ok().as_deref(),
+        env::var("TERM").ok().as_deref(),
+    )
+}
+
+/// Truecolor is announced by `COLORTERM`, basic terminals known by `TERM`
+/// get 16 colors, everything else falls back to 256 colors
+pub fn color_support_from(
+    colorterm: Option<&str>,
+    term: Option<&str>,
+) -> ColorMode {
+    if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+        return ColorMode::TrueColor;
+    }
+    match term {
+        Some(term) if term.contains("direct") => ColorMode::TrueColor,
+        Some(term) if term.contains("256color") => ColorMode::Ansi256,
+        Some("linux") | Some("vt100") | Some("vt220") | Some("ansi") => {
+            ColorMode::Ansi16
+        }
+        _ => ColorMode::Ansi256,
+    }
+}
+
+/// Convert rgb color to closest one available in given mode, other colors
+/// are passed as is
+pub fn convert(color: Color, mode: ColorMode) -> Color {
+    match (color, mode) {
+        (Color::Rgb { r, g, b }, ColorMode::Ansi256) => {
+            Color::AnsiValue(rgb_to_256(r, g, b))
+        }
+        (Color::Rgb { r, g, b }, ColorMode::Ansi16) => rgb_to_16(r, g, b),
+        _ => color,
+    }
+}
+
+/// Rgb value of any color, named ones take usual xterm values and reset
+/// is treated as light grey foreground
+pub fn to_rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::AnsiValue(index) => ansi_to_rgb(index),
+        Color::Reset => (192, 192, 192),
+        named => ANSI_16
+            .iter()
+            .find(|(color, _)| *color == named)
+            .map(|(_, rgb)| *rgb)
+            .unwrap_or((192, 192, 192)),
+    }
+}
+
+/// Fully saturated color of the hue, 0.0 and 1.0 are both red
+pub fn hue_to_rgb(hue: f32) -> (u8, u8, u8) {
+    let h = hue.rem_euclid(1.0) * 6.0;
+    let rising = ((h % 1.0) * 255.0) as u8;
+    let falling = 255 - rising;
+    match h as u8 {
+        0 => (255, rising, 0),
+        1 => (falling, 255, 0),
+        2 => (0, 255, rising),
+        3 => (0, falling, 255),
+        4 => (rising, 0, 255),
+        _ => (255, 0, falling),
+    }
+}
+
+fn ansi_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI_16[index as usize].1,
+        16..=231 => {
+            let cube = (index - 16) as usize;
+            (
+                CUBE_LEVELS[cube / 36],
+                CUBE_LEVELS[(cube / 6) % 6],
+                CUBE_LEVELS[cube % 6],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// Index of closest color from 6x6x6 cube or grayscale ramp of 256 palette
+pub fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    // grayscale ramp 232..=255 goes from 8 to 238 with step 10
+    let average = (r as usize + g as usize + b as usize) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23);
+    let gray_level = (8 + gray_step * 10) as u8;
+    let gray_index = 232 + gray_step;
+
+    if distance((r, g, b), (gray_level, gray_level, gray_level))
+        < distance((r, g, b), cube)
+    {
+        gray_index as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+/// Closest of basic 16 colors
+pub fn rgb_to_16(r: u8, g: u8, b: u8) -> Color {
+    ANSI_16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::White)
+}
+
+fn cube_index(value: u8) -> usize {
+    CUBE_LEVELS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, level)| (**level as i32 - value as i32).abs())
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let dr = a.0 as i32 - b.0 as i32;
+    let dg = a.1 as i32 - b.1 as i32;
+    let db = a.2 as i32 - b.2 as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_color() {
+        assert_eq!(
+            parse_color("#FF8000"),
+            Ok(Color::Rgb {
+                r: 255,
+                g: 128,
+                b: 0
+            })
+        );
+        assert_eq!(
+            parse_color("#0a0B0c"),
+            Ok(Color::Rgb {
+                r: 10,
+                g: 11,
+                b: 12
+            })
+        );
+        assert!(parse_color("#FF80").is_err());
+        assert!(parse_color("#GG0000").is_err());
+        assert!(parse_color("#ééé").is_err());
+    }
+
+    #[test]
+    fn parse_rgb_color() {
+        assert_eq!(
+            parse_color("rgb(1, 2,3)"),
+            Ok(Color::Rgb { r: 1, g: 2, b: 3 })
+        );
+        assert!(parse_color("rgb(1,2)").is_err());
+        assert!(parse_color("rgb(1,2,3,4)").is_err());
+        assert!(parse_color("rgb(256,0,0)").is_err());
+        assert!(parse_color("rgb(1,2,3").is_err());
+    }
+
+    #[test]
+    fn parse_named_color() {
+        assert_eq!(parse_color("green"), Ok(Color::Green));
+        assert_eq!(parse_color("dark_grey"), Ok(Color::DarkGrey));
+        assert!(parse_color("greenish").is_err());
+        assert!(parse_color("").is_err());
+    }
+
+    #[test]
+    fn hue_wheel() {
+        assert_eq!(hue_to_rgb(0.0), (255, 0, 0));
+        assert_eq!(hue_to_rgb(1.0 / 3.0), (0, 255, 0));
+        assert_eq!(hue_to_rgb(2.0 / 3.0), (0, 0, 255));
+        assert_eq!(hue_to_rgb(1.0), (255, 0, 0));
+    }
+
+    #[test]
+    fn parse_mode() {
+        assert_eq!("true".parse::<ColorMode>(), Ok(ColorMode::TrueColor));
+        assert_eq!("256".parse::<ColorMode>(), Ok(ColorMode::Ansi256));
+        assert_eq!("16".parse::<ColorMode>(), Ok(ColorMode::Ansi16));
+        assert!("8".parse::<ColorMode>().is_err());
+    }
+
+    #[test]
+    fn detect_from_env() {
+        assert_eq!(
+            color_support_from(Some("truecolor"), Some("xterm")),
+            ColorMode::TrueColor
+        );
+        assert_eq!(
+            color_support_from(None, Some("xterm-256color")),
+            ColorMode::Ansi256
+        );
+        assert_eq!(
+            color_support_from(None, Some("xterm-direct")),
+            ColorMode::TrueColor
+        );
+        assert_eq!(color_support_from(None, Some("linux")), ColorMode::Ansi16);
+        assert_eq!(color_support_from(None, None), ColorMode::Ansi256);
+    }
+
+    #[test]
+    fn rgb_to_palette_256() {
+        assert_eq!(rgb_to_256(0, 0, 0), 16);
+        assert_eq!(rgb_to_256(255, 255, 255), 231);
+        assert_eq!(rgb_to_256(255, 0, 0), 196);
+        assert_eq!(rgb_to_256(0, 255, 0), 46);
+        // grays go to grayscale ramp
+        assert_eq!(rgb_to_256(128, 128, 128), 244);
+    }
+
+    #[test]
+    fn rgb_to_basic_16() {
+        assert_eq!(rgb_to_16(250, 10, 10), Color::Red);
+        assert_eq!(rgb_to_16(0, 120, 0), Color::DarkGreen);
+        assert_eq!(rgb_to_16(5, 5, 5), Color::Black);
+    }
+
+    #[test]
+    fn any_color_to_rgb() {
+        assert_eq!(to_rgb(Color::Rgb { r: 1, g: 2, b: 3 }), (1, 2, 3));
+        assert_eq!(to_rgb(Color::Red), (255, 0, 0));
+        assert_eq!(to_rgb(Color::AnsiValue(196)), (255, 0, 0));
+        assert_eq!(to_rgb(Color::AnsiValue(244)), (128, 128, 128));
+        assert_eq!(to_rgb(Color::AnsiValue(4)), (0, 0, 128));
+    }
+
+    #[test]
+    fn truecolor_and_named_colors_untouched() {
+        let rgb = Color::Rgb { r: 1, g: 2, b: 3 };
+        assert_eq!(convert(rgb, ColorMode::TrueColor), rgb);
+        assert_eq!(convert(Color::Green, ColorMode::Ansi16), Color::Green);
+        assert_eq!(convert(rgb, ColorMode::Ansi256), Color::AnsiValue(16));
+    }
+}
@@ -1,15 +1,105 @@
-use crate::buffer::Cell;
+use crate::audio::AudioLevel;
+use crate::buffer::{Buffer, Cell};
+use crate::color::{self, ColorMode};
 use crossterm::{
     cursor, event,
     style::{self, Stylize},
     terminal, QueueableCommand,
 };
+use derive_builder::Builder;
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
 use std::{
-    io::{BufWriter, Result, Write},
-    time::Duration,
+    io::{self, BufWriter, Result, Write},
+    panic, thread,
+    time::{Duration, Instant},
 };
 
+/// Random generator used by effects
+pub type EffectRng = StdRng;
+
+/// Frame rate main loop tries to keep
+pub const TARGET_FPS: f64 = 60.0;
+
+/// Effect is reset only after terminal stopped changing size for this time
+pub const RESIZE_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Names of effects which can be run from command line and `--check`
+pub const VALID_SAVERS: &[&str] = &[
+    "matrix", "life", "maze", "scope", "brain", "wator", "sand", "ant", "eca",
+    "mandel", "globe", "clock", "blank",
+];
+
+/// Create generator for effect, seeded one gives reproducible frames,
+/// without seed it's initialized from system entropy as usual
+pub fn make_rng(seed: Option<u64>) -> EffectRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Switch terminal to raw mode with hidden cursor, everything is restored
+/// when guard is dropped or by panic hook installed for guard's lifetime
+pub struct TerminalGuard {
+    alt_screen: bool,
+}
+
+impl TerminalGuard {
+    /// Without `alt_screen` effect is drawn over normal screen, so frames
+    /// stay in the scrollback, useful for debugging
+    pub fn new(alt_screen: bool) -> Result<Self> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        if alt_screen {
+            stdout.queue(terminal::EnterAlternateScreen)?;
+        }
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+        stdout.flush()?;
+
+        // restore terminal before panic message is printed, otherwise it's
+        // lost on alternate screen or garbled by raw mode
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal(alt_screen);
+            default_hook(info);
+        }));
+
+        Ok(Self { alt_screen })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            // hook already restored terminal
+            return;
+        }
+        // back to default hook, which prints panic message as usual
+        let _ = panic::take_hook();
+        restore_terminal(self.alt_screen);
+    }
+}
+
+fn restore_terminal(alt_screen: bool) {
+    let mut stdout = io::stdout();
+    let _ = stdout.queue(cursor::Show);
+    if alt_screen {
+        let _ = stdout.queue(terminal::Clear(terminal::ClearType::All));
+        let _ = stdout.queue(terminal::LeaveAlternateScreen);
+    }
+    let _ = stdout.flush();
+    let _ = terminal::disable_raw_mode();
+}
+
 pub trait TerminalEffect {
+    /// Short name of the effect, same as used on command line
+    fn name(&self) -> &'static str;
+    /// Smallest terminal size (width, height) effect can be drawn in
+    fn min_size(&self) -> (u16, u16) {
+        (1, 1)
+    }
     /// get difference between frames, this is used to minimize screen updates
     fn get_diff(&mut self) -> Vec<(usize, usize, Cell)>;
     /// Update to next frame
@@ -18,27 +108,678 @@ pub trait TerminalEffect {
     fn update_size(&mut self, width: u16, height: u16);
     /// Reset effect, i think it's useful in case of size/options update
     fn reset(&mut self);
+    /// Adapt to new screen size, effects which can keep their state
+    /// override it, the rest start over. Terminal is cleared after resize,
+    /// so next diff should draw the whole frame
+    fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        self.reset();
+    }
+    /// Audio level in 0.0..=1.0 in reactive mode, set before each update,
+    /// effects which don't react to sound just ignore it
+    fn set_intensity(&mut self, _intensity: f32) {}
+    /// Key pressed by user which is not handled by main loop itself, it's
+    /// ignored by default. Interactive ones are `life` (space drops gliders)
+    /// and `scope` (arrows change frequencies of oscillators)
+    fn on_key(&mut self, _key: event::KeyEvent) {}
+}
+
+/// Boxed effects are picked at runtime, like in cycle mode
+impl<T: TerminalEffect + ?Sized> TerminalEffect for Box<T> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn min_size(&self) -> (u16, u16) {
+        (**self).min_size()
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        (**self).get_diff()
+    }
+
+    fn update(&mut self) {
+        (**self).update()
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        (**self).update_size(width, height)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+
+    fn resize(&mut self, width: u16, height: u16) {
+        (**self).resize(width, height)
+    }
+
+    fn set_intensity(&mut self, intensity: f32) {
+        (**self).set_intensity(intensity)
+    }
+
+    fn on_key(&mut self, key: event::KeyEvent) {
+        (**self).on_key(key)
+    }
+}
+
+/// Source of time for the main loop, abstracted to drive time in tests
+pub trait Clock {
+    /// Time passed since clock creation
+    fn now(&self) -> Duration;
+    /// Block for given duration
+    fn sleep(&mut self, duration: Duration);
+}
+
+/// Real clock backed by `Instant` and `thread::sleep`
+pub struct SystemClock {
+    started_at: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Keep frames duration stable and measure actual frame rate
+pub struct FramePacer<C: Clock> {
+    clock: C,
+    target_frame_duration: Duration,
+    frame_started_at: Duration,
+    frames_per_second: Option<f64>,
+}
+
+impl<C: Clock> FramePacer<C> {
+    pub fn new(clock: C, target_fps: f64) -> Self {
+        let frame_started_at = clock.now();
+        Self {
+            clock,
+            target_frame_duration: Duration::from_secs_f64(1.0 / target_fps),
+            frame_started_at,
+            frames_per_second: None,
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_started_at = self.clock.now();
+    }
+
+    /// Sleep rest of the frame if required and update fps estimation
+    pub fn end_frame(&mut self) -> f64 {
+        let delta = self.clock.now().saturating_sub(self.frame_started_at);
+        if delta < self.target_frame_duration {
+            self.clock.sleep(self.target_frame_duration - delta);
+        };
+
+        // calculate actual frame rate
+        let delta = self.clock.now().saturating_sub(self.frame_started_at);
+        let current = 1.0 / delta.as_secs_f64().max(f64::EPSILON);
+        let fps = match self.frames_per_second {
+            Some(fps) => (fps + current) / 2.0,
+            None => current,
+        };
+        self.frames_per_second = Some(fps);
+        fps
+    }
+
+    pub fn frames_per_second(&self) -> f64 {
+        self.frames_per_second.unwrap_or(0.0)
+    }
+
+    pub fn now(&self) -> Duration {
+        self.clock.now()
+    }
+}
+
+/// Collapse burst of resize events sent while window border is dragged,
+/// only the last size is applied once events stopped for a delay
+pub struct ResizeDebouncer {
+    delay: Duration,
+    pending: Option<((u16, u16), Duration)>,
+}
+
+impl ResizeDebouncer {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: None,
+        }
+    }
+
+    pub fn resize(&mut self, size: (u16, u16), now: Duration) {
+        self.pending = Some((size, now));
+    }
+
+    /// Size to apply if there were no resizes for the delay
+    pub fn ready(&mut self, now: Duration) -> Option<(u16, u16)> {
+        match self.pending {
+            Some((size, resized_at))
+                if now.saturating_sub(resized_at) >= self.delay =>
+            {
+                self.pending = None;
+                Some(size)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Tracks time of the last input to tell when user became idle
+pub struct IdleTimer {
+    timeout: Duration,
+    last_input_at: Duration,
 }
 
-pub fn process_input() -> Result<bool> {
-    if event::poll(Duration::from_millis(10))? {
-        if let event::Event::Key(keyevent) = event::read()? {
-            if keyevent
-                == event::KeyEvent::new(
-                    event::KeyCode::Char('q'),
-                    event::KeyModifiers::NONE,
-                )
-                || keyevent
-                    == event::KeyEvent::new(
-                        event::KeyCode::Esc,
-                        event::KeyModifiers::NONE,
-                    )
+impl IdleTimer {
+    pub fn new(now: Duration, timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_input_at: now,
+        }
+    }
+
+    /// Restart waiting, called on every input
+    pub fn input(&mut self, now: Duration) {
+        self.last_input_at = now;
+    }
+
+    pub fn is_idle(&self, now: Duration) -> bool {
+        now.saturating_sub(self.last_input_at) >= self.timeout
+    }
+}
+
+/// Keep screen blank until there is no input for the timeout, resize
+/// events are passed to the effect while waiting
+pub fn wait_for_idle<TE, C>(
+    effect: &mut TE,
+    timeout: Duration,
+    clock: &C,
+) -> Result<()>
+where
+    TE: TerminalEffect,
+    C: Clock,
+{
+    let mut timer = IdleTimer::new(clock.now(), timeout);
+    while !timer.is_idle(clock.now()) {
+        if event::poll(Duration::from_millis(100))? {
+            match event::read()? {
+                event::Event::Resize(width, height) => {
+                    effect.resize(width, height);
+                }
+                event::Event::Key(_) | event::Event::Mouse(_) => {
+                    timer.input(clock.now())
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check if key should stop the effect. Raw mode turns Ctrl-C into key
+/// press instead of SIGINT, so it's handled here and loop returns through
+/// `TerminalGuard` which restores terminal
+#[inline]
+pub fn is_quit_key(key: &event::KeyEvent) -> bool {
+    match key.modifiers {
+        event::KeyModifiers::NONE => {
+            matches!(key.code, event::KeyCode::Char('q') | event::KeyCode::Esc)
+        }
+        event::KeyModifiers::CONTROL => key.code == event::KeyCode::Char('c'),
+        _ => false,
+    }
+}
+
+/// Collapse repeated key presses received during one frame, holding a key
+/// produces bunch of events and each of them should not be applied separately
+pub fn debounce_keys(keys: &[event::KeyEvent]) -> Vec<event::KeyEvent> {
+    let mut unique: Vec<event::KeyEvent> = Vec::with_capacity(keys.len());
+    for key in keys
+        .iter()
+        .filter(|key| key.kind != event::KeyEventKind::Release)
+    {
+        if !unique
+            .iter()
+            .any(|k| k.code == key.code && k.modifiers == key.modifiers)
+        {
+            unique.push(*key);
+        }
+    }
+    unique
+}
+
+/// Key to show or hide stats overlay
+pub fn is_overlay_key(key: &event::KeyEvent) -> bool {
+    key.modifiers == event::KeyModifiers::NONE
+        && key.code == event::KeyCode::Char('i')
+}
+
+/// Text of stats overlay drawn in the top-left corner
+pub fn overlay_text(
+    name: &str,
+    fps: f64,
+    frame: usize,
+    screen_size: (u16, u16),
+) -> String {
+    format!(
+        " {} fps: {:.1} frame: {} size: {}x{} ",
+        name, fps, frame, screen_size.0, screen_size.1
+    )
+}
+
+/// Put back cells of the screen hidden under previous overlay and draw new
+/// one on top, returns width of drawn overlay to be cleared next frame
+pub fn draw_overlay<W: Write>(
+    stdout: &mut W,
+    screen: &Buffer,
+    prev_width: usize,
+    text: Option<&str>,
+    color_mode: ColorMode,
+    invert: bool,
+) -> Result<usize> {
+    let hidden: Vec<(usize, usize, Cell)> = (0..prev_width.min(screen.width))
+        .map(|x| (x, 0, screen.get(x, 0)))
+        .collect();
+    render_diff(stdout, &hidden, color_mode, invert)?;
+
+    let Some(text) = text else {
+        return Ok(0);
+    };
+    let visible: String = text.chars().take(screen.width).collect();
+    stdout.queue(cursor::MoveTo(0, 0))?;
+    stdout.queue(style::PrintStyledContent(
+        visible.as_str().attribute(style::Attribute::Reverse),
+    ))?;
+    Ok(visible.chars().count())
+}
+
+/// Consecutive cells of the same row sharing color and attribute
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub x: usize,
+    pub y: usize,
+    pub text: String,
+    pub color: style::Color,
+    pub attr: style::Attribute,
+    pub bg: style::Color,
+}
+
+/// Group diff into runs, so each of them printed with single cursor move
+pub fn group_runs(diff: &[(usize, usize, Cell)]) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (x, y, cell) in diff.iter() {
+        if let Some(run) = runs.last_mut() {
+            if run.y == *y
+                && run.x + run.text.chars().count() == *x
+                && run.color == cell.color
+                && run.attr == cell.attr
+                && run.bg == cell.bg
+            {
+                run.text.push(cell.symbol);
+                continue;
+            }
+        }
+        runs.push(Run {
+            x: *x,
+            y: *y,
+            text: cell.symbol.to_string(),
+            color: cell.color,
+            attr: cell.attr,
+            bg: cell.bg,
+        });
+    }
+    runs
+}
+
+/// Queue diff to the output batched in runs, colors are converted to ones
+/// supported in given color mode, with `invert` foreground and background
+/// of every cell are swapped
+pub fn render_diff<W: Write>(
+    stdout: &mut W,
+    diff: &[(usize, usize, Cell)],
+    color_mode: ColorMode,
+    invert: bool,
+) -> Result<()> {
+    let runs = match color_mode {
+        ColorMode::TrueColor => group_runs(diff),
+        _ => {
+            let converted: Vec<(usize, usize, Cell)> = diff
+                .iter()
+                .map(|(x, y, cell)| {
+                    let color = color::convert(cell.color, color_mode);
+                    let bg = color::convert(cell.bg, color_mode);
+                    (*x, *y, Cell { color, bg, ..*cell })
+                })
+                .collect();
+            group_runs(&converted)
+        }
+    };
+    for run in runs {
+        stdout.queue(cursor::MoveTo(run.x as u16, run.y as u16))?;
+        let content = run.text.with(run.color).attribute(run.attr);
+        let content = match run.bg {
+            style::Color::Reset => content,
+            bg => content.on(bg),
+        };
+        // attributes are reset after each run, so terminal is left as is
+        let content = match invert {
+            true => content.attribute(style::Attribute::Reverse),
+            false => content,
+        };
+        stdout.queue(style::PrintStyledContent(content))?;
+    }
+    Ok(())
+}
+
+/// Put background under cells which have none, symbols colored the same
+/// as background get contrasting color to stay visible
+pub fn apply_background(diff: &mut [(usize, usize, Cell)], bg: style::Color) {
+    let (r, g, b) = color::to_rgb(bg);
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    let contrast = match luminance > 128.0 {
+        true => style::Color::Black,
+        false => style::Color::White,
+    };
+    for (_, _, cell) in diff.iter_mut() {
+        if cell.bg == style::Color::Reset {
+            cell.bg = bg;
+        }
+        if color::to_rgb(cell.color) == color::to_rgb(cell.bg) {
+            cell.color = contrast;
+        }
+    }
+}
+
+/// Largest shift of the panned frame from its place, columns and rows
+const PAN_AMPLITUDE: (f64, f64) = (4.0, 2.0);
+
+/// Seconds of one horizontal and one vertical swing of the pan, different
+/// so frame moves along Lissajous figure
+const PAN_PERIOD: (f64, f64) = (240.0, 180.0);
+
+/// Shift of the frame after given time of panning
+pub fn pan_offset(elapsed: Duration) -> (i32, i32) {
+    let t = elapsed.as_secs_f64() * 2.0 * std::f64::consts::PI;
+    (
+        (PAN_AMPLITUDE.0 * (t / PAN_PERIOD.0).sin()).round() as i32,
+        (PAN_AMPLITUDE.1 * (t / PAN_PERIOD.1).sin()).round() as i32,
+    )
+}
+
+/// Moves whole frame of effect around the screen against burn-in, effect
+/// keeps drawing in its own coordinates
+pub struct Pan {
+    /// Frame as effect has drawn it
+    frame: Buffer,
+    /// Frame shifted as it was put on screen
+    panned: Buffer,
+}
+
+impl Pan {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            frame: Buffer::new(width, height),
+            panned: Buffer::new(width, height),
+        }
+    }
+
+    /// Screen is cleared on resize, so both frames start empty
+    pub fn resize(&mut self, width: usize, height: usize) {
+        *self = Self::new(width, height);
+    }
+
+    /// Turn diff of effect into diff of the screen shifted by offset, cells
+    /// moved off screen are cut
+    pub fn apply(
+        &mut self,
+        diff: &[(usize, usize, Cell)],
+        (dx, dy): (i32, i32),
+    ) -> Vec<(usize, usize, Cell)> {
+        for (x, y, cell) in diff.iter() {
+            self.frame.set(*x, *y, *cell);
+        }
+        let mut shifted = Buffer::new(self.frame.width, self.frame.height);
+        for (index, cell) in self.frame.iter().enumerate() {
+            let (x, y) = self.frame.pos_of(index);
+            let (x, y) = (x as i32 + dx, y as i32 + dy);
+            if (0..shifted.width as i32).contains(&x)
+                && (0..shifted.height as i32).contains(&y)
             {
-                return Ok(false);
+                shifted.set(x as usize, y as usize, *cell);
             }
         }
+        let diff = self.panned.diff(&shifted);
+        self.panned = shifted;
+        diff
+    }
+}
+
+/// Every cell of the screen filled with background
+fn background_frame(
+    (width, height): (u16, u16),
+    bg: style::Color,
+) -> Vec<(usize, usize, Cell)> {
+    let mut frame = Buffer::new(width as usize, height as usize);
+    frame.fill_with(&Cell {
+        bg,
+        ..Cell::default()
+    });
+    frame
+        .iter()
+        .enumerate()
+        .map(|(index, cell)| {
+            let (x, y) = frame.pos_of(index);
+            (x, y, *cell)
+        })
+        .collect()
+}
+
+/// Serialize symbols of the buffer to text grid, rows separated by newline
+pub fn buffer_to_string(buffer: &Buffer) -> String {
+    let mut grid = String::with_capacity((buffer.width + 1) * buffer.height);
+    for y in 0..buffer.height {
+        if y > 0 {
+            grid.push('\n');
+        }
+        for x in 0..buffer.width {
+            grid.push(buffer.get(x, y).symbol);
+        }
+    }
+    grid
+}
+
+/// Serialize buffer with ANSI escape codes for colors and attributes, rows
+/// separated by newline
+pub fn buffer_to_ansi(buffer: &Buffer) -> String {
+    let mut grid = String::new();
+    for y in 0..buffer.height {
+        if y > 0 {
+            grid.push('\n');
+        }
+        let row: Vec<(usize, usize, Cell)> = (0..buffer.width)
+            .map(|x| (x, y, buffer.get(x, y)))
+            .collect();
+        for run in group_runs(&row) {
+            grid.push_str(
+                &run.text
+                    .as_str()
+                    .with(run.color)
+                    .attribute(run.attr)
+                    .to_string(),
+            );
+        }
     }
-    Ok(true)
+    grid
+}
+
+/// Complete frame after the first update of the effect. Some effects,
+/// like sand, draw nothing until they are updated, so diff of the first
+/// frame alone may be empty
+pub fn first_frame(
+    effect: &mut impl TerminalEffect,
+    screen_size: (u16, u16),
+) -> Buffer {
+    let mut screen = Buffer::new(screen_size.0 as usize, screen_size.1 as usize);
+    for (x, y, cell) in effect.get_diff() {
+        screen.set(x, y, cell);
+    }
+    effect.update();
+    for (x, y, cell) in effect.get_diff() {
+        screen.set(x, y, cell);
+    }
+    screen
+}
+
+/// Draw complete frame over the screen and put cursor on the last row, so
+/// the frame stays visible after exit
+pub fn render_once<W: Write>(
+    stdout: &mut W,
+    effect: &mut impl TerminalEffect,
+    screen_size: (u16, u16),
+    color_mode: ColorMode,
+    invert: bool,
+) -> Result<()> {
+    let frame = first_frame(effect, screen_size);
+    let cells: Vec<(usize, usize, Cell)> = frame
+        .iter()
+        .enumerate()
+        .map(|(index, cell)| {
+            let (x, y) = frame.pos_of(index);
+            (x, y, *cell)
+        })
+        .collect();
+    stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+    render_diff(stdout, &cells, color_mode, invert)?;
+    stdout.queue(cursor::MoveTo(0, screen_size.1.saturating_sub(1)))?;
+    stdout.flush()
+}
+
+/// Complete frame of newly created effect as plain text, see `first_frame`,
+/// then move effect to the next frame. Later diffs only hold changes, use
+/// `check::capture_frames` to follow an effect over several frames
+pub fn render_frame(
+    effect: &mut impl TerminalEffect,
+    screen_size: (u16, u16),
+) -> String {
+    buffer_to_string(&draw_frame(effect, screen_size))
+}
+
+/// Same as `render_frame`, but keeps colors as ANSI escape codes
+pub fn render_frame_ansi(
+    effect: &mut impl TerminalEffect,
+    screen_size: (u16, u16),
+) -> String {
+    buffer_to_ansi(&draw_frame(effect, screen_size))
+}
+
+fn draw_frame(effect: &mut impl TerminalEffect, screen_size: (u16, u16)) -> Buffer {
+    let screen = first_frame(effect, screen_size);
+    effect.update();
+    screen
+}
+
+/// Word which has to be typed to stop the loop, other keys are ignored
+#[derive(Debug, Clone, Default)]
+pub struct KeyLock {
+    word: Vec<char>,
+    /// Last typed characters, not longer than the word
+    typed: Vec<char>,
+}
+
+impl KeyLock {
+    pub fn new(word: &str) -> Self {
+        Self {
+            word: word.chars().collect(),
+            typed: vec![],
+        }
+    }
+
+    /// Remember typed character, true when the word is matched
+    pub fn feed(&mut self, key: &event::KeyEvent) -> bool {
+        let event::KeyCode::Char(character) = key.code else {
+            return false;
+        };
+        if key.kind == event::KeyEventKind::Release {
+            return false;
+        }
+        self.typed.push(character);
+        if self.typed.len() > self.word.len() {
+            self.typed.remove(0);
+        }
+        self.typed == self.word
+    }
+}
+
+/// Process keys pressed during the frame, return false if should stop.
+/// Without lock `q`, `Esc` or Ctrl-C stop the loop, with lock only typed
+/// word does
+pub fn process_input(keys: &[event::KeyEvent], lock: Option<&mut KeyLock>) -> bool {
+    match lock {
+        Some(lock) => !keys.iter().any(|key| lock.feed(key)),
+        None => !keys.iter().any(is_quit_key),
+    }
+}
+
+/// Settings of the main loop, shared by all effects
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct LoopOptions {
+    /// Stop after given number of frames, runs until quit key if not set
+    #[builder(default)]
+    iterations: Option<usize>,
+    #[builder(default)]
+    color_mode: ColorMode,
+    /// Word to type for exit, quit keys are ignored when set
+    #[builder(default)]
+    lock: Option<String>,
+    /// Start effect only after no input for this time, then any key quits
+    #[builder(default)]
+    idle: Option<Duration>,
+    /// Background color painted under effect instead of the terminal one
+    #[builder(default)]
+    background: Option<style::Color>,
+    /// Level of captured audio passed to effect each frame
+    #[builder(default)]
+    audio_level: Option<AudioLevel>,
+    /// Swap foreground and background of the whole screen
+    #[builder(default)]
+    invert: bool,
+    /// Slowly move whole frame around against burn-in
+    #[builder(default)]
+    pan: bool,
+    /// Any key quits like a real screensaver, used by cycle mode, effect
+    /// doesn't get keys then
+    #[builder(default)]
+    any_key_quits: bool,
+}
+
+/// Summary of the finished run, terminal size is the last one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunStats {
+    pub effect: &'static str,
+    pub fps: f64,
+    pub frames: usize,
+    pub width: u16,
+    pub height: u16,
 }
 
 pub fn run_loop<W, TE>(
@@ -50,14 +791,57 @@ where
     W: Write,
     TE: TerminalEffect,
 {
-    let (width, height) = terminal::size()?;
+    let options = LoopOptions {
+        iterations,
+        ..Default::default()
+    };
+    run_loop_with_options(stdout, effect, &options).map(|stats| stats.fps)
+}
+
+pub fn run_loop_with_options<W, TE>(
+    stdout: &mut W,
+    effect: &mut TE,
+    options: &LoopOptions,
+) -> Result<RunStats>
+where
+    W: Write,
+    TE: TerminalEffect,
+{
+    run_loop_with_clock(stdout, effect, options, SystemClock::new())
+}
+
+pub fn run_loop_with_clock<W, TE, C>(
+    stdout: &mut W,
+    effect: &mut TE,
+    options: &LoopOptions,
+    clock: C,
+) -> Result<RunStats>
+where
+    W: Write,
+    TE: TerminalEffect,
+    C: Clock,
+{
+    if let Some(timeout) = options.idle {
+        wait_for_idle(effect, timeout, &clock)?;
+    }
+
+    let (mut width, mut height) = terminal::size()?;
 
-    // #[cfg(test)]
     let mut iters: usize = 0;
 
+    // what effect has drawn so far, used to restore cells under overlay
+    let mut screen = Buffer::new(width as usize, height as usize);
+    let mut paint_background = true;
+    let mut pan = options
+        .pan
+        .then(|| Pan::new(width as usize, height as usize));
+    let mut show_overlay = false;
+    let mut overlay_width = 0;
+
+    let mut lock = options.lock.as_deref().map(KeyLock::new);
     let mut is_running = true;
-    let mut frames_per_second = 0.0;
-    let target_frame_duration = Duration::from_secs_f64(1.0 / 60.0_f64);
+    let mut pacer = FramePacer::new(clock, TARGET_FPS);
+    let mut resizes = ResizeDebouncer::new(RESIZE_DEBOUNCE);
 
     // wrap in buffer due to tests "run_loop_fps_gte_0" failing on CI/CD
     // NOTE: 12/Dec/2023 issue with tests of CI/CD still not resolved
@@ -65,61 +849,558 @@ where
 
     // main loop
     while is_running {
-        let started_at: std::time::SystemTime = std::time::SystemTime::now();
-        is_running = process_input()?;
+        pacer.begin_frame();
 
-        #[allow(clippy::single_match)]
+        // drain all pending events, keys collected to be debounced
+        let mut keys = vec![];
         while event::poll(Duration::from_millis(10))? {
             match event::read()? {
                 event::Event::Resize(new_width, new_height) => {
-                    // Update size and reset effect
-                    effect.update_size(new_width, new_height);
-                    effect.reset();
+                    resizes.resize((new_width, new_height), pacer.now());
                 }
+                event::Event::Key(keyevent) => keys.push(keyevent),
                 _ => {}
             }
         }
+        // Resize effect once window stopped changing, terminal may have
+        // garbled content after resize, so it's cleared and redrawn
+        if let Some((new_width, new_height)) = resizes.ready(pacer.now()) {
+            effect.resize(new_width, new_height);
+            (width, height) = (new_width, new_height);
+            buffered_stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+            screen = Buffer::new(width as usize, height as usize);
+            if let Some(pan) = pan.as_mut() {
+                pan.resize(width as usize, height as usize);
+            }
+            overlay_width = 0;
+            paint_background = true;
+        }
+        // typed word may have repeated characters, so lock gets all keys
+        is_running = process_input(&keys, lock.as_mut());
+        let keys = debounce_keys(&keys);
+        // started by idle timer or cycling, so any input wakes user up
+        let any_key_quits = options.any_key_quits || options.idle.is_some();
+        if any_key_quits && lock.is_none() && !keys.is_empty() {
+            is_running = false;
+        }
+        if lock.is_none() && keys.iter().any(is_overlay_key) {
+            show_overlay = !show_overlay;
+        }
+        // in kiosk, idle and cycle modes keys are only for exit
+        if lock.is_none() && !any_key_quits {
+            for key in keys
+                .iter()
+                .filter(|key| !is_quit_key(key) && !is_overlay_key(key))
+            {
+                effect.on_key(*key);
+            }
+        }
 
         // draw diff
-        let queue = effect.get_diff();
-        for item in queue.iter() {
-            let (x, y, cell) = item;
+        let mut queue = effect.get_diff();
+        if let Some(pan) = pan.as_mut() {
+            let elapsed = Duration::from_secs_f64(iters as f64 / TARGET_FPS);
+            queue = pan.apply(&queue, pan_offset(elapsed));
+        }
+        // effects draw only what changed, so the rest of the screen is
+        // filled with background once, inverted one as well
+        if paint_background && (options.background.is_some() || options.invert) {
+            let bg = options.background.unwrap_or(style::Color::Reset);
+            let frame = background_frame((width, height), bg);
+            for (x, y, cell) in frame.iter() {
+                screen.set(*x, *y, *cell);
+            }
+            render_diff(
+                &mut buffered_stdout,
+                &frame,
+                options.color_mode,
+                options.invert,
+            )?;
+            paint_background = false;
+        }
+        if let Some(bg) = options.background {
+            apply_background(&mut queue, bg);
+        }
+        for (x, y, cell) in queue.iter() {
             debug_assert!(*x < width as usize && *y < height as usize);
-            buffered_stdout.queue(cursor::MoveTo(*x as u16, *y as u16))?;
-            buffered_stdout.queue(style::PrintStyledContent(
-                cell.symbol.with(cell.color).attribute(cell.attr),
-            ))?;
+            screen.set(*x, *y, *cell);
+        }
+        render_diff(
+            &mut buffered_stdout,
+            &queue,
+            options.color_mode,
+            options.invert,
+        )?;
+        if show_overlay || overlay_width > 0 {
+            let text = overlay_text(
+                effect.name(),
+                pacer.frames_per_second(),
+                iters,
+                (width, height),
+            );
+            overlay_width = draw_overlay(
+                &mut buffered_stdout,
+                &screen,
+                overlay_width,
+                show_overlay.then_some(text.as_str()),
+                options.color_mode,
+                options.invert,
+            )?;
         }
         buffered_stdout.flush()?;
+        if let Some(level) = &options.audio_level {
+            effect.set_intensity(level.get());
+        }
         effect.update();
 
         // stabilize fps if requred
-        let ended_at = std::time::SystemTime::now();
-        let delta = ended_at
-            .duration_since(started_at)
-            .unwrap_or(std::time::Duration::from_secs(0));
-        if delta < target_frame_duration {
-            std::thread::sleep(target_frame_duration - delta);
+        pacer.end_frame();
+
+        iters += 1;
+        if let Some(iterations) = options.iterations {
+            if iters > iterations {
+                is_running = false;
+            }
         };
+    }
+    Ok(RunStats {
+        effect: effect.name(),
+        fps: pacer.frames_per_second(),
+        frames: iters,
+        width,
+        height,
+    })
+}
 
-        // calculate actual frame rate
-        let ended_at = std::time::SystemTime::now();
-        let delta = ended_at
-            .duration_since(started_at)
-            .unwrap_or(std::time::Duration::from_secs(0));
-        frames_per_second = (frames_per_second + (1.0 / delta.as_secs_f64())) / 2.0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::Cell, rc::Rc};
+
+    /// Clock which moves only when asked, sleeping advances it instantly
+    #[derive(Clone, Default)]
+    struct MockClock {
+        now: Rc<Cell<Duration>>,
+        slept: Rc<Cell<Duration>>,
+    }
 
-        if delta < target_frame_duration {
-            std::thread::sleep(target_frame_duration - delta);
+    impl MockClock {
+        fn advance(&self, duration: Duration) {
+            self.now.set(self.now.get() + duration);
         }
+    }
 
-        // #[cfg(test)]
-        if let Some(iterations) = iterations {
-            iters += 1;
-            if iters > iterations {
-                is_running = false;
-            }
+    impl Clock for MockClock {
+        fn now(&self) -> Duration {
+            self.now.get()
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.slept.set(self.slept.get() + duration);
+            self.advance(duration);
+        }
+    }
+
+    #[test]
+    fn pacer_sleeps_rest_of_frame() {
+        let clock = MockClock::default();
+        let mut pacer = FramePacer::new(clock.clone(), 50.0);
+
+        pacer.begin_frame();
+        clock.advance(Duration::from_millis(5));
+        let fps = pacer.end_frame();
+
+        // only one sleep for the rest of 20ms frame
+        assert_eq!(clock.slept.get(), Duration::from_millis(15));
+        assert!((fps - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pacer_no_sleep_for_slow_frames() {
+        let clock = MockClock::default();
+        let mut pacer = FramePacer::new(clock.clone(), 50.0);
+
+        pacer.begin_frame();
+        clock.advance(Duration::from_millis(40));
+        let fps = pacer.end_frame();
+
+        assert_eq!(clock.slept.get(), Duration::ZERO);
+        assert!((fps - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pacer_fps_average() {
+        let clock = MockClock::default();
+        let mut pacer = FramePacer::new(clock.clone(), 50.0);
+        assert_eq!(pacer.frames_per_second(), 0.0);
+
+        for _ in 0..10 {
+            pacer.begin_frame();
+            clock.advance(Duration::from_millis(1));
+            pacer.end_frame();
+        }
+        assert!((pacer.frames_per_second() - 50.0).abs() < 1e-9);
+
+        pacer.begin_frame();
+        clock.advance(Duration::from_millis(40));
+        let fps = pacer.end_frame();
+        assert!((fps - 37.5).abs() < 1e-9);
+    }
+
+    fn key(c: char) -> event::KeyEvent {
+        event::KeyEvent::new(event::KeyCode::Char(c), event::KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn debounce_identical_keys() {
+        let keys = vec![key('+'); 10];
+        let debounced = debounce_keys(&keys);
+        assert_eq!(debounced.len(), 1);
+        assert_eq!(debounced[0].code, event::KeyCode::Char('+'));
+    }
+
+    #[test]
+    fn debounce_keeps_order_of_distinct_keys() {
+        let keys = vec![key('+'), key('-'), key('+'), key('-'), key('q')];
+        let debounced = debounce_keys(&keys);
+        let codes: Vec<event::KeyCode> = debounced.iter().map(|k| k.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                event::KeyCode::Char('+'),
+                event::KeyCode::Char('-'),
+                event::KeyCode::Char('q')
+            ]
+        );
+    }
+
+    #[test]
+    fn quit_on_q_or_esc() {
+        assert!(!process_input(&[key('q')], None));
+        assert!(!process_input(
+            &[event::KeyEvent::new(
+                event::KeyCode::Esc,
+                event::KeyModifiers::NONE
+            )],
+            None
+        ));
+        assert!(process_input(&[key('+')], None));
+        assert!(process_input(&[], None));
+    }
+
+    #[test]
+    fn quit_on_ctrl_c() {
+        let ctrl_c = event::KeyEvent::new(
+            event::KeyCode::Char('c'),
+            event::KeyModifiers::CONTROL,
+        );
+        assert!(!process_input(&[key('a'), ctrl_c], None));
+        assert!(process_input(&[key('c')], None));
+
+        // kiosk mode can't be left without the word
+        let mut lock = KeyLock::new("abc");
+        assert!(process_input(&[ctrl_c], Some(&mut lock)));
+    }
+
+    #[test]
+    fn background_under_cells_without_one() {
+        use crate::buffer::Cell;
+
+        let green = Cell::new('a', style::Color::Green, style::Attribute::Reset);
+        let on_red = Cell {
+            bg: style::Color::Red,
+            ..green
         };
+        let mut diff = vec![(0, 0, Cell::default()), (1, 0, green), (2, 0, on_red)];
+        apply_background(&mut diff, style::Color::White);
+
+        // default black symbol is kept, it's visible on white
+        assert_eq!(diff[0].2.bg, style::Color::White);
+        assert_eq!(diff[0].2.color, style::Color::Black);
+        assert_eq!(diff[1].2.bg, style::Color::White);
+        assert_eq!(diff[2].2.bg, style::Color::Red);
+
+        // symbols of background color are switched to contrasting one
+        let mut diff = vec![(0, 0, Cell::default())];
+        apply_background(&mut diff, style::Color::Black);
+        assert_eq!(diff[0].2.color, style::Color::White);
+    }
+
+    #[test]
+    fn invert_reverses_and_resets_every_run() {
+        use crate::buffer::Cell;
+
+        let bold = Cell::new('a', style::Color::Green, style::Attribute::Bold);
+        let diff = vec![(0, 0, bold), (5, 0, Cell::default())];
+        let render = |invert| {
+            let mut out = Vec::new();
+            render_diff(&mut out, &diff, ColorMode::TrueColor, invert).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let inverted = render(true);
+        assert_eq!(inverted.matches("\x1b[7m").count(), 2);
+        // own attribute of the cell is kept
+        assert!(inverted.contains("\x1b[1m"));
+        assert!(inverted.ends_with("\x1b[0m"));
+        assert!(!render(false).contains("\x1b[7m"));
+    }
+
+    #[test]
+    fn pan_moves_whole_frame() {
+        use crate::buffer::Cell;
+
+        assert_eq!(pan_offset(Duration::ZERO), (0, 0));
+        assert_eq!(pan_offset(Duration::from_secs(60)), (4, 2));
+        assert_eq!(pan_offset(Duration::from_secs(120)), (0, -2));
+
+        let a = Cell::new('a', style::Color::Green, style::Attribute::Bold);
+        let mut pan = Pan::new(10, 5);
+        assert_eq!(pan.apply(&[(0, 0, a), (9, 4, a)], (0, 0)).len(), 2);
+
+        // frame moved, old place is cleared and cell at the edge is cut
+        let diff = pan.apply(&[], (1, 1));
+        assert_eq!(
+            diff,
+            vec![(0, 0, Cell::default()), (1, 1, a), (9, 4, Cell::default())]
+        );
+
+        // nothing changed, nothing to draw
+        assert!(pan.apply(&[], (1, 1)).is_empty());
+    }
+
+    #[test]
+    fn stats_as_json() {
+        let stats = RunStats {
+            effect: "matrix",
+            fps: 59.5,
+            frames: 120,
+            width: 80,
+            height: 24,
+        };
+        assert_eq!(
+            serde_json::to_string(&stats).unwrap(),
+            r#"{"effect":"matrix","fps":59.5,"frames":120,"width":80,"height":24}"#
+        );
+    }
+
+    #[test]
+    fn resize_applied_after_events_stop() {
+        let clock = MockClock::default();
+        let mut resizes = ResizeDebouncer::new(RESIZE_DEBOUNCE);
+        assert_eq!(resizes.ready(clock.now()), None);
+
+        resizes.resize((80, 24), clock.now());
+        clock.advance(Duration::from_millis(100));
+        resizes.resize((90, 30), clock.now());
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(resizes.ready(clock.now()), None);
+
+        // only the last size, and only once
+        clock.advance(Duration::from_millis(50));
+        assert_eq!(resizes.ready(clock.now()), Some((90, 30)));
+        assert_eq!(resizes.ready(clock.now()), None);
+    }
+
+    #[test]
+    fn idle_after_timeout_without_input() {
+        let clock = MockClock::default();
+        let mut timer = IdleTimer::new(clock.now(), Duration::from_secs(300));
+        clock.advance(Duration::from_secs(200));
+        assert!(!timer.is_idle(clock.now()));
+
+        // input restarts waiting
+        timer.input(clock.now());
+        clock.advance(Duration::from_secs(200));
+        assert!(!timer.is_idle(clock.now()));
+        clock.advance(Duration::from_secs(100));
+        assert!(timer.is_idle(clock.now()));
+    }
+
+    #[test]
+    fn locked_until_word_typed() {
+        let mut lock = KeyLock::new("moo");
+        assert!(process_input(&[key('q')], Some(&mut lock)));
+        assert!(process_input(
+            &[event::KeyEvent::new(
+                event::KeyCode::Esc,
+                event::KeyModifiers::NONE
+            )],
+            Some(&mut lock)
+        ));
+        assert!(process_input(&[key('m'), key('o')], Some(&mut lock)));
+        // word is matched across frames and after wrong characters
+        assert!(!process_input(&[key('o')], Some(&mut lock)));
+
+        let mut lock = KeyLock::new("moo");
+        assert!(process_input(&[key('m'), key('m')], Some(&mut lock)));
+        assert!(!process_input(&[key('o'), key('o')], Some(&mut lock)));
+    }
+
+    #[test]
+    fn overlay_drawn_and_cleared() {
+        let mut screen = Buffer::new(8, 2);
+        for (x, symbol) in "abcdefgh".chars().enumerate() {
+            screen.set(
+                x,
+                0,
+                crate::buffer::Cell::new(
+                    symbol,
+                    style::Color::Green,
+                    style::Attribute::Reset,
+                ),
+            );
+        }
+
+        // overlay is cut to the screen width
+        let mut out = Vec::new();
+        let drawn = draw_overlay(
+            &mut out,
+            &screen,
+            0,
+            Some("fps: 60.0 frame: 1"),
+            ColorMode::TrueColor,
+            false,
+        )
+        .unwrap();
+        assert_eq!(drawn, 8);
+        assert!(String::from_utf8(out).unwrap().contains("fps: 60."));
+
+        // hidden overlay puts back effect cells
+        let mut out = Vec::new();
+        assert_eq!(
+            draw_overlay(
+                &mut out,
+                &screen,
+                drawn,
+                None,
+                ColorMode::TrueColor,
+                false
+            )
+            .unwrap(),
+            0
+        );
+        let out = String::from_utf8(out).unwrap();
+        for symbol in "abcdefgh".chars() {
+            assert!(out.contains(symbol));
+        }
+    }
+
+    #[test]
+    fn overlay_toggle_key() {
+        assert!(is_overlay_key(&key('i')));
+        assert!(!is_overlay_key(&key('q')));
+        assert_eq!(
+            overlay_text("life", 59.95, 10, (80, 24)),
+            " life fps: 60.0 frame: 10 size: 80x24 "
+        );
+    }
+
+    #[test]
+    fn diff_grouped_in_runs() {
+        let green = crate::buffer::Cell::new(
+            'a',
+            style::Color::Green,
+            style::Attribute::Reset,
+        );
+        let red = crate::buffer::Cell::new(
+            'b',
+            style::Color::Red,
+            style::Attribute::Reset,
+        );
+        let diff = vec![
+            (0, 0, green),
+            (1, 0, green),
+            (2, 0, red),
+            (4, 0, red),
+            (5, 0, red),
+            (6, 1, red),
+            (0, 2, green),
+        ];
+        let runs = group_runs(&diff);
+        let spans: Vec<(usize, usize, &str)> = runs
+            .iter()
+            .map(|run| (run.x, run.y, run.text.as_str()))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (0, 0, "aa"),
+                (2, 0, "b"),
+                (4, 0, "bb"),
+                (6, 1, "b"),
+                (0, 2, "a")
+            ]
+        );
+        assert_eq!(runs[1].color, style::Color::Red);
+        assert!(group_runs(&[]).is_empty());
+    }
+
+    #[test]
+    fn once_draws_whole_matrix_frame() {
+        use crate::rain::digital_rain::{DigitalRain, DigitalRainOptionsBuilder};
+
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((40, 10))
+            .drops_range((20, 30))
+            .speed_range((2, 16))
+            .seed(Some(1))
+            .build()
+            .unwrap();
+        let frame = first_frame(&mut DigitalRain::new(options.clone()), (40, 10));
+        let symbols: Vec<char> = frame
+            .iter()
+            .map(|cell| cell.symbol)
+            .filter(|symbol| *symbol != ' ')
+            .collect();
+        assert!(symbols.len() > 10);
+
+        let mut out = Vec::new();
+        let mut rain = DigitalRain::new(options);
+        render_once(&mut out, &mut rain, (40, 10), ColorMode::TrueColor, false)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(symbols.iter().all(|symbol| out.contains(*symbol)));
+    }
+
+    #[test]
+    fn render_frame_as_text() {
+        let options = crate::scope::ScopeOptionsBuilder::default()
+            .screen_size((16, 5))
+            .build()
+            .unwrap();
+        let mut scope = crate::scope::Scope::new(options);
+        let text = render_frame(&mut scope, (16, 5));
+        assert_eq!(text.lines().count(), 5);
+        assert!(text.lines().all(|line| line.chars().count() == 16));
+        assert!(text.chars().any(|c| ('\u{2801}'..='\u{28ff}').contains(&c)));
+
+        // matrix is the default effect, its snapshot is not blank
+        let options =
+            crate::rain::digital_rain::DigitalRainOptionsBuilder::default()
+                .screen_size((30, 8))
+                .drops_range((10, 20))
+                .speed_range((2, 16))
+                .seed(Some(1))
+                .build()
+                .unwrap();
+        let mut rain = crate::rain::digital_rain::DigitalRain::new(options.clone());
+        let text = render_frame(&mut rain, (30, 8));
+        assert_eq!(text.lines().count(), 8);
+        assert!(text.chars().filter(|c| !c.is_whitespace()).count() > 10);
+        let mut rain = crate::rain::digital_rain::DigitalRain::new(options);
+        assert!(render_frame_ansi(&mut rain, (30, 8)).contains('\x1b'));
+
+        let mut screen = Buffer::new(3, 2);
+        screen.fill_with(&crate::buffer::Cell::new(
+            '#',
+            style::Color::Green,
+            style::Attribute::Reset,
+        ));
+        assert_eq!(buffer_to_string(&screen), "###\n###");
+        let ansi = buffer_to_ansi(&screen);
+        assert!(ansi.contains("###"));
+        assert!(ansi.contains('\x1b'));
+        assert_eq!(ansi.lines().count(), 2);
     }
-    Ok(frames_per_second)
 }
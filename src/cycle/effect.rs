@@ -0,0 +1,218 @@
+//! Screensaver rotation, plays each of the effects for a while and switches
+//! to the next one, looping until user quits.
+use crate::buffer::{Buffer, Cell};
+use crate::common::{Clock, SystemClock, TerminalEffect};
+use crossterm::event;
+use derive_builder::Builder;
+use std::time::Duration;
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct CycleOptions {
+    screen_size: (u16, u16),
+    /// How long each effect is played
+    #[builder(default = "Duration::from_secs(30)")]
+    interval: Duration,
+}
+
+pub struct Cycle<C: Clock = SystemClock> {
+    options: CycleOptions,
+    effects: Vec<Box<dyn TerminalEffect>>,
+    /// Index of the effect played now
+    current: usize,
+    clock: C,
+    switched_at: Duration,
+    /// Screen still shows previous effect, next frame is drawn in full
+    clear_screen: bool,
+}
+
+impl<C: Clock> TerminalEffect for Cycle<C> {
+    fn name(&self) -> &'static str {
+        self.effects[self.current].name()
+    }
+
+    /// Big enough for any of the effects
+    fn min_size(&self) -> (u16, u16) {
+        self.effects.iter().fold((1, 1), |(width, height), effect| {
+            let (w, h) = effect.min_size();
+            (width.max(w), height.max(h))
+        })
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        let diff = self.effects[self.current].get_diff();
+        if !self.clear_screen {
+            return diff;
+        }
+        self.clear_screen = false;
+
+        // cells not drawn by new effect are cleared
+        let mut frame = Buffer::new(
+            self.options.screen_size.0 as usize,
+            self.options.screen_size.1 as usize,
+        );
+        for (x, y, cell) in diff {
+            frame.set(x, y, cell);
+        }
+        frame
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| {
+                let (x, y) = frame.pos_of(index);
+                (x, y, *cell)
+            })
+            .collect()
+    }
+
+    fn update(&mut self) {
+        self.effects[self.current].update();
+
+        let now = self.clock.now();
+        if now.saturating_sub(self.switched_at) >= self.options.interval {
+            self.current = (self.current + 1) % self.effects.len();
+            // effect remembers what it has drawn before, screen is cleared
+            // so it starts over
+            self.effects[self.current].reset();
+            self.switched_at = now;
+            self.clear_screen = true;
+        }
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+        for effect in self.effects.iter_mut() {
+            effect.update_size(width, height);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.effects[self.current].reset();
+    }
+
+    fn resize(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+        for effect in self.effects.iter_mut() {
+            effect.resize(width, height);
+        }
+    }
+
+    fn set_intensity(&mut self, intensity: f32) {
+        self.effects[self.current].set_intensity(intensity);
+    }
+
+    fn on_key(&mut self, key: event::KeyEvent) {
+        self.effects[self.current].on_key(key);
+    }
+}
+
+impl Cycle {
+    /// Effects are played in given order, there should be at least one
+    pub fn new(
+        options: CycleOptions,
+        effects: Vec<Box<dyn TerminalEffect>>,
+    ) -> Self {
+        Self::with_clock(options, effects, SystemClock::new())
+    }
+}
+
+impl<C: Clock> Cycle<C> {
+    /// Create rotation driven by provided clock
+    pub fn with_clock(
+        options: CycleOptions,
+        effects: Vec<Box<dyn TerminalEffect>>,
+        clock: C,
+    ) -> Self {
+        assert!(!effects.is_empty(), "nothing to cycle through");
+        Self {
+            options,
+            effects,
+            current: 0,
+            switched_at: clock.now(),
+            clock,
+            clear_screen: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blank::{Blank, BlankOptionsBuilder};
+    use crate::scope::{Scope, ScopeOptionsBuilder};
+    use std::{cell::Cell as StdCell, rc::Rc};
+
+    /// Clock moved by hand
+    #[derive(Clone, Default)]
+    struct ManualClock(Rc<StdCell<Duration>>);
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.0.set(self.0.get() + duration);
+        }
+    }
+
+    fn get_cycle(clock: ManualClock) -> Cycle<ManualClock> {
+        let size = (20, 10);
+        let effects: Vec<Box<dyn TerminalEffect>> = vec![
+            Box::new(Scope::new(
+                ScopeOptionsBuilder::default()
+                    .screen_size(size)
+                    .build()
+                    .unwrap(),
+            )),
+            Box::new(Blank::new(
+                BlankOptionsBuilder::default()
+                    .screen_size(size)
+                    .build()
+                    .unwrap(),
+            )),
+        ];
+        let options = CycleOptionsBuilder::default()
+            .screen_size(size)
+            .interval(Duration::from_secs(30))
+            .build()
+            .unwrap();
+        Cycle::with_clock(options, effects, clock)
+    }
+
+    #[test]
+    fn switches_after_interval() {
+        let mut clock = ManualClock::default();
+        let mut cycle = get_cycle(clock.clone());
+        assert_eq!(cycle.name(), "scope");
+
+        clock.sleep(Duration::from_secs(29));
+        cycle.update();
+        assert_eq!(cycle.name(), "scope");
+
+        clock.sleep(Duration::from_secs(1));
+        cycle.update();
+        assert_eq!(cycle.name(), "blank");
+
+        // and loops back to the first one
+        clock.sleep(Duration::from_secs(30));
+        cycle.update();
+        assert_eq!(cycle.name(), "scope");
+    }
+
+    #[test]
+    fn screen_cleared_on_switch() {
+        let mut clock = ManualClock::default();
+        let mut cycle = get_cycle(clock.clone());
+        let first = cycle.get_diff();
+        assert!(first.len() < 200);
+
+        clock.sleep(Duration::from_secs(30));
+        cycle.update();
+        cycle.update();
+        // blank draws nothing, so whole screen is cleared
+        let diff = cycle.get_diff();
+        assert_eq!(diff.len(), 200);
+        assert!(diff.iter().all(|(_, _, cell)| *cell == Cell::default()));
+        assert!(cycle.get_diff().is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+//! Elementary cellular automaton, each generation is a row computed from
+//! the previous one, new generations appear at the bottom and older ones
+//! scroll up. State of the cell is bit of the rule number picked by
+//! states of the cell and its two neighbors, so rule 110 maps each of
+//! eight neighborhoods to bits of 0b01101110.
+use crate::buffer::{Buffer, Cell};
+use crate::common::{make_rng, EffectRng, TerminalEffect};
+use crossterm::style;
+use derive_builder::Builder;
+use rand::Rng;
+use std::collections::VecDeque;
+
+const LIVE_CELL: Cell = Cell {
+    symbol: '█',
+    color: style::Color::Rgb {
+        r: 120,
+        g: 200,
+        b: 255,
+    },
+    attr: style::Attribute::Reset,
+    bg: style::Color::Reset,
+};
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into), build_fn(validate = "Self::validate"))]
+pub struct EcaOptions {
+    screen_size: (u16, u16),
+    /// Wolfram code of the automaton, 0 to 255
+    #[builder(default = "110")]
+    rule: u16,
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    seed: Option<u64>,
+}
+
+impl EcaOptionsBuilder {
+    fn validate(&self) -> Result<(), String> {
+        match self.rule {
+            Some(rule) if rule > 255 => {
+                Err(format!("rule {} is out of 0..=255 range", rule))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct Eca {
+    options: EcaOptions,
+    buffer: Buffer,
+    /// Next frame is drawn here and swapped with `buffer`
+    back_buffer: Buffer,
+    /// Last generations, the newest one is at the back
+    rows: VecDeque<Vec<bool>>,
+    rng: EffectRng,
+}
+
+impl TerminalEffect for Eca {
+    fn name(&self) -> &'static str {
+        "eca"
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        self.back_buffer.clear();
+        fill_buffer(&self.rows, &mut self.back_buffer);
+
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
+        diff
+    }
+
+    fn update(&mut self) {
+        let last = self.rows.back().unwrap();
+        let mut next = next_generation(last, self.options.rule as u8);
+        // dead row stays dead forever, start over from random one
+        if !next.contains(&true) {
+            next = random_row(next.len(), &mut self.rng);
+        }
+
+        self.rows.push_back(next);
+        if self.rows.len() > self.buffer.height {
+            self.rows.pop_front();
+        }
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        let new_effect = Self::with_rng(self.options.clone(), self.rng.clone());
+        *self = new_effect;
+    }
+
+    /// Generations are cut or padded with dead cells to new width, only
+    /// those fitting new height are kept
+    fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        let (width, height) = (width as usize, height as usize);
+        for row in self.rows.iter_mut() {
+            row.resize(width, false);
+        }
+        while self.rows.len() > height.max(1) {
+            self.rows.pop_front();
+        }
+        self.buffer = Buffer::new(width, height);
+        self.back_buffer = self.buffer.clone();
+    }
+}
+
+impl Eca {
+    /// Printed by `tarts eca --help`
+    pub fn help() -> &'static str {
+        "eca: elementary cellular automaton scrolling up
+
+Options:
+  --rule <n>        rule number 0..255, 110 by default
+  --seed <n>        seed random generator
+"
+    }
+
+    pub fn new(options: EcaOptions) -> Self {
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Create effect with provided random generator
+    pub fn with_rng(options: EcaOptions, mut rng: EffectRng) -> Self {
+        let buffer = Buffer::new(
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        let rows = VecDeque::from([random_row(buffer.width, &mut rng)]);
+
+        Self {
+            options,
+            back_buffer: buffer.clone(),
+            buffer,
+            rows,
+            rng,
+        }
+    }
+}
+
+fn random_row(width: usize, rng: &mut EffectRng) -> Vec<bool> {
+    (0..width).map(|_| rng.gen_bool(0.5)).collect()
+}
+
+/// Apply rule to every cell of the row, edges are wrapped around
+pub fn next_generation(row: &[bool], rule: u8) -> Vec<bool> {
+    let width = row.len();
+    (0..width)
+        .map(|x| {
+            let left = row[(x + width - 1) % width] as u8;
+            let center = row[x] as u8;
+            let right = row[(x + 1) % width] as u8;
+            rule & (1 << (left << 2 | center << 1 | right)) != 0
+        })
+        .collect()
+}
+
+/// Draw rows aligned to the bottom of the screen, newest one is the last
+pub fn fill_buffer(rows: &VecDeque<Vec<bool>>, buffer: &mut Buffer) {
+    let top = buffer.height.saturating_sub(rows.len());
+    for (y, row) in rows.iter().rev().take(buffer.height).rev().enumerate() {
+        for (x, live) in row.iter().take(buffer.width).enumerate() {
+            if *live {
+                buffer.set(x, top + y, LIVE_CELL);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(row: &str) -> Vec<bool> {
+        row.chars().map(|c| c == '#').collect()
+    }
+
+    #[test]
+    fn rule_110_generation() {
+        let row = parse("....#...##..");
+        assert_eq!(next_generation(&row, 110), parse("...##..###.."));
+        // rule 90 gives sierpinski triangle
+        assert_eq!(next_generation(&parse("..#.."), 90), parse(".#.#."));
+    }
+
+    #[test]
+    fn rule_out_of_range_rejected() {
+        let options = EcaOptionsBuilder::default()
+            .screen_size((10, 5))
+            .rule(256_u16)
+            .build();
+        assert!(options.is_err());
+    }
+
+    #[test]
+    fn rows_scroll_up() {
+        let options = EcaOptionsBuilder::default()
+            .screen_size((12, 3))
+            .seed(Some(1))
+            .build()
+            .unwrap();
+        let mut eca = Eca::new(options);
+        for _ in 0..5 {
+            eca.update();
+        }
+        assert_eq!(eca.rows.len(), 3);
+
+        let second = eca.rows[1].clone();
+        eca.update();
+        assert_eq!(eca.rows[0], second);
+        let _ = eca.get_diff();
+        for (x, live) in eca.rows[2].iter().enumerate() {
+            assert_eq!(eca.buffer.get(x, 2).symbol == '█', *live);
+        }
+    }
+}
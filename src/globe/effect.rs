@@ -0,0 +1,238 @@
+//! Spinning Earth, every cell inside of the disc is cast to the point of the
+//! sphere, its latitude and longitude pick land or water from the built-in
+//! map, brightness comes from angle to the light.
+use crate::buffer::{Buffer, Cell};
+use crate::common::TerminalEffect;
+use crossterm::style;
+use derive_builder::Builder;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Equirectangular map, 6 degrees of longitude per column from -180 and
+/// 9 degrees of latitude per row from the north pole
+const WORLD_MAP: [&str; 20] = [
+    "....................####....................................",
+    "..........################......##...........#####..........",
+    "..#################.#####......#############################",
+    "...###########..#####.#......#.##########################...",
+    ".......###############.......#########################......",
+    ".........#########..........#########################.......",
+    "..........#######..........##########################.......",
+    "............##..#..........#############.##########.........",
+    "..............##...........############...##..###.#.........",
+    "................######.......#########........####..........",
+    "................########.......######..........#######......",
+    ".................#######........######............#####.....",
+    "..................#####.........####.#...........#######....",
+    "..................####...........##..............#######....",
+    "..................##..................................#...#.",
+    "..................#.........................................",
+    "............................................................",
+    "#########...........##....##################################",
+    "############################################################",
+    "############################################################",
+];
+
+/// Terminal cells are about twice higher than wide
+const CELL_ASPECT: f32 = 2.0;
+
+/// Night side is not completely dark
+const AMBIENT: f32 = 0.15;
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct GlobeOptions {
+    screen_size: (u16, u16),
+    /// Angle globe turns each frame, radians
+    #[builder(default = "0.03")]
+    rotation_speed: f32,
+    /// Tilt of the axis towards viewer, radians
+    #[builder(default = "0.4")]
+    tilt: f32,
+}
+
+pub struct Globe {
+    options: GlobeOptions,
+    buffer: Buffer,
+    /// Next frame is drawn here and swapped with `buffer`
+    back_buffer: Buffer,
+    /// Angle globe turned by, it spins eastward like the Earth
+    angle: f32,
+}
+
+impl TerminalEffect for Globe {
+    fn name(&self) -> &'static str {
+        "globe"
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        self.back_buffer.clear();
+        self.fill_buffer();
+
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
+        diff
+    }
+
+    fn update(&mut self) {
+        self.angle = (self.angle + self.options.rotation_speed) % (2.0 * PI);
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.options.clone());
+    }
+
+    /// Globe keeps spinning from the same angle
+    fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        self.buffer = Buffer::new(width as usize, height as usize);
+        self.back_buffer = self.buffer.clone();
+    }
+}
+
+impl Globe {
+    /// Printed by `tarts globe --help`
+    pub fn help() -> &'static str {
+        "globe: spinning Earth lit from the side
+
+Has no options.
+"
+    }
+
+    pub fn new(options: GlobeOptions) -> Self {
+        let buffer = Buffer::new(
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        Self {
+            options,
+            back_buffer: buffer.clone(),
+            buffer,
+            angle: 0.0,
+        }
+    }
+
+    fn fill_buffer(&mut self) {
+        let (width, height) = (
+            self.back_buffer.width as f32,
+            self.back_buffer.height as f32,
+        );
+        // radius in cell widths, globe fits the screen with small margin
+        let radius = (width / 2.0).min(height * CELL_ASPECT / 2.0) * 0.9;
+        let light = normalize((-0.5, 0.5, 0.7));
+        let (sin_tilt, cos_tilt) = self.options.tilt.sin_cos();
+
+        for y in 0..self.back_buffer.height {
+            for x in 0..self.back_buffer.width {
+                let nx = (x as f32 + 0.5 - width / 2.0) / radius;
+                let ny = -(y as f32 + 0.5 - height / 2.0) * CELL_ASPECT / radius;
+                let d = nx * nx + ny * ny;
+                if d > 1.0 {
+                    continue;
+                }
+                let nz = (1.0 - d).sqrt();
+
+                // undo tilt around x axis to get point in globe coordinates
+                let gy = ny * cos_tilt + nz * sin_tilt;
+                let gz = nz * cos_tilt - ny * sin_tilt;
+                let lat = gy.clamp(-1.0, 1.0).asin();
+                let lon = nx.atan2(gz) - self.angle;
+
+                let brightness = (nx * light.0 + ny * light.1 + nz * light.2)
+                    .max(0.0)
+                    .mul_add(1.0 - AMBIENT, AMBIENT);
+                let cell = if is_land(lat, lon) {
+                    Cell::new(
+                        '#',
+                        shade((60, 200, 70), brightness),
+                        style::Attribute::Bold,
+                    )
+                } else {
+                    Cell::new(
+                        '~',
+                        shade((40, 90, 220), brightness),
+                        style::Attribute::NormalIntensity,
+                    )
+                };
+                self.back_buffer.set(x, y, cell);
+            }
+        }
+    }
+}
+
+/// Look up the map, latitude and longitude in radians, longitude wraps
+pub fn is_land(lat: f32, lon: f32) -> bool {
+    let rows = WORLD_MAP.len();
+    let columns = WORLD_MAP[0].len();
+    let row = ((FRAC_PI_2 - lat) / PI * rows as f32) as usize;
+    let column =
+        ((lon + PI).rem_euclid(2.0 * PI) / (2.0 * PI) * columns as f32) as usize;
+    WORLD_MAP[row.min(rows - 1)].as_bytes()[column.min(columns - 1)] == b'#'
+}
+
+fn normalize(v: (f32, f32, f32)) -> (f32, f32, f32) {
+    let length = (v.0 * v.0 + v.1 * v.1 + v.2 * v.2).sqrt();
+    (v.0 / length, v.1 / length, v.2 / length)
+}
+
+fn shade(rgb: (u8, u8, u8), brightness: f32) -> style::Color {
+    let scale = |c: u8| (c as f32 * brightness) as u8;
+    style::Color::Rgb {
+        r: scale(rgb.0),
+        g: scale(rgb.1),
+        b: scale(rgb.2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_globe() -> Globe {
+        let options = GlobeOptionsBuilder::default()
+            .screen_size((60, 30))
+            .build()
+            .unwrap();
+        Globe::new(options)
+    }
+
+    #[test]
+    fn continents_on_map() {
+        let deg = |d: f32| d.to_radians();
+        // Africa, Pacific, Antarctica and Atlantic
+        assert!(is_land(deg(0.0), deg(20.0)));
+        assert!(!is_land(deg(0.0), deg(-150.0)));
+        assert!(is_land(deg(-85.0), deg(100.0)));
+        assert!(!is_land(deg(30.0), deg(-40.0)));
+        // longitude wraps around
+        assert_eq!(
+            is_land(deg(0.0), deg(20.0 + 360.0)),
+            is_land(deg(0.0), deg(20.0))
+        );
+    }
+
+    #[test]
+    fn globe_has_land_and_water() {
+        let mut globe = get_globe();
+        globe.get_diff();
+        let symbols: Vec<char> = globe.buffer.iter().map(|c| c.symbol).collect();
+        assert!(symbols.contains(&'#'));
+        assert!(symbols.contains(&'~'));
+        // corners are outside of the globe
+        assert_eq!(globe.buffer.get(0, 0), Cell::default());
+        assert_eq!(globe.buffer.get(59, 29), Cell::default());
+    }
+
+    #[test]
+    fn rotation_changes_frame() {
+        let mut globe = get_globe();
+        globe.get_diff();
+        for _ in 0..10 {
+            globe.update();
+        }
+        assert!(!globe.get_diff().is_empty());
+    }
+}
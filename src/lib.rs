@@ -1,7 +1,69 @@
+//! # tarts
+//!
+//! Terminal screen savers as a library. Every effect implements
+//! [`TerminalEffect`], so it can be driven from your own loop: take the diff
+//! of the frame, draw it, then move the effect to the next frame.
+//!
+//! ```
+//! use tarts::{DigitalRain, DigitalRainOptionsBuilder, TerminalEffect};
+//!
+//! let options = DigitalRainOptionsBuilder::default()
+//!     .screen_size((80, 24))
+//!     .drops_range((20, 40))
+//!     .speed_range((2, 16))
+//!     .build()
+//!     .unwrap();
+//! let mut rain = DigitalRain::new(options);
+//!
+//! for _ in 0..10 {
+//!     for (x, y, cell) in rain.get_diff() {
+//!         // draw cell.symbol with cell.color at (x, y)
+//!         let _ = (x, y, cell.symbol);
+//!     }
+//!     rain.update();
+//! }
+//! ```
+//!
+//! [`common::run_loop`] does the same on a real terminal.
+pub mod ant;
+pub mod audio;
 pub mod blank;
+pub mod braille;
+pub mod brain;
 pub mod buffer;
 pub mod check;
+pub mod clock;
+pub mod color;
 pub mod common;
+pub mod cycle;
+pub mod eca;
+pub mod globe;
 pub mod life;
+pub mod mandel;
 pub mod maze;
 pub mod rain;
+pub mod record;
+pub mod sand;
+pub mod scope;
+pub mod wator;
+
+pub use ant::{AntOptions, AntOptionsBuilder, Langton};
+pub use blank::{Blank, BlankOptions, BlankOptionsBuilder};
+pub use brain::{Brain, BrainOptions, BrainOptionsBuilder};
+pub use buffer::{Buffer, Cell};
+// clock effect is reached as `clock::Clock`, so it's not mistaken for
+// `common::Clock` driving the main loop
+pub use clock::{ClockOptions, ClockOptionsBuilder};
+pub use common::TerminalEffect;
+pub use cycle::{Cycle, CycleOptions, CycleOptionsBuilder};
+pub use eca::{Eca, EcaOptions, EcaOptionsBuilder};
+pub use globe::{Globe, GlobeOptions, GlobeOptionsBuilder};
+pub use life::{ConwayLife, ConwayLifeOptions, ConwayLifeOptionsBuilder};
+pub use mandel::{Mandel, MandelOptions, MandelOptionsBuilder};
+pub use maze::{Maze, MazeOptions, MazeOptionsBuilder};
+pub use rain::digital_rain::{
+    DigitalRain, DigitalRainOptions, DigitalRainOptionsBuilder,
+};
+pub use sand::{Sand, SandOptions, SandOptionsBuilder};
+pub use scope::{Scope, ScopeOptions, ScopeOptionsBuilder};
+pub use wator::{Wator, WatorOptions, WatorOptionsBuilder};
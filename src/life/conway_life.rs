@@ -11,38 +11,71 @@
 //!     If a dead cell is surrounded by exactly three living cells,
 //!     it becomes a living cell.
 use crate::buffer::{Buffer, Cell};
-use crate::common::TerminalEffect;
-use crossterm::style;
+use crate::common::{make_rng, EffectRng, TerminalEffect};
+use crate::life::pattern::parse_pattern;
+use crossterm::{event, style};
 use derive_builder::Builder;
 use once_cell::sync::Lazy;
 use rand::Rng;
 use std::collections::HashMap;
 
+/// Number of gliders dropped by space key
+const GLIDERS_ON_KEY: usize = 5;
+
 static DEAD_CELLS_CHARS: Lazy<Vec<char>> = Lazy::new(|| {
     let characters = "ﾊﾐﾋｰｳｼﾅﾓﾆｻﾜﾂｵﾘｱﾎﾃﾏｹﾒｴｶｷﾑﾕﾗｾﾈｽﾀﾇﾍ";
     let char_vec: Vec<char> = characters.chars().collect();
     char_vec
 });
 
-#[derive(Builder, Default, Debug)]
-#[builder(public, setter(into))]
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into), build_fn(validate = "Self::validate"))]
 pub struct ConwayLifeOptions {
     screen_size: (u16, u16),
     #[builder(default = "3000")]
     initial_cells: u32,
+    /// Wrap neighbors around screen edges, so gliders fly through the borders
+    #[builder(default)]
+    wrap_edges: bool,
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    seed: Option<u64>,
+    /// Number of frames dead cells fade through darker greens, 0 removes
+    /// them right away
+    #[builder(default)]
+    fade_frames: u8,
+    /// Plaintext or RLE pattern placed at the center instead of random
+    /// cells, no random gliders are added then
+    #[builder(default)]
+    seed_pattern: Option<String>,
+}
+
+impl ConwayLifeOptionsBuilder {
+    fn validate(&self) -> Result<(), String> {
+        match &self.seed_pattern {
+            Some(Some(pattern)) => parse_pattern(pattern).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct LifeCell {
     pub character: char,
     pub color: style::Color,
+    /// Frames left for dead cell to fade out
+    pub health: u8,
 }
 
 pub struct ConwayLife {
     options: ConwayLifeOptions,
     buffer: Buffer,
+    /// Next frame is drawn here and swapped with `buffer`
+    back_buffer: Buffer,
     cells: HashMap<(usize, usize), LifeCell>,
-    pub rng: rand::prelude::ThreadRng,
+    /// Dead cells which are still fading out
+    fading: HashMap<(usize, usize), LifeCell>,
+    pub rng: EffectRng,
     pub current_gen: u8,
 }
 
@@ -51,14 +84,31 @@ impl LifeCell {
         Self {
             character,
             color: style::Color::Rgb { r: 0, g: 255, b: 0 },
+            health: 0,
         }
     }
 
-    pub fn update_color_and_char(
-        &mut self,
-        rng: &mut rand::prelude::ThreadRng,
-        current_gen: u8,
-    ) {
+    /// Make dead cell one step darker, false when it has faded out
+    pub fn fade(&mut self) -> bool {
+        if self.health == 0 {
+            return false;
+        }
+        if let style::Color::Rgb { r, g, b } = self.color {
+            let dim = |channel: u8| {
+                (channel as u16 * self.health as u16 / (self.health as u16 + 1))
+                    as u8
+            };
+            self.color = style::Color::Rgb {
+                r: dim(r),
+                g: dim(g),
+                b: dim(b),
+            };
+        }
+        self.health -= 1;
+        true
+    }
+
+    pub fn update_color_and_char(&mut self, rng: &mut impl Rng, current_gen: u8) {
         let green_color = 255_u8.wrapping_sub(current_gen);
         match current_gen {
             0..=230 => {
@@ -84,62 +134,42 @@ impl LifeCell {
 }
 
 impl TerminalEffect for ConwayLife {
+    fn name(&self) -> &'static str {
+        "life"
+    }
+
+    /// Room for a glider
+    fn min_size(&self) -> (u16, u16) {
+        (3, 3)
+    }
+
     fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
-        let mut curr_buffer = Buffer::new(
-            self.options.screen_size.0 as usize,
-            self.options.screen_size.1 as usize,
-        );
+        self.back_buffer.clear();
 
         // fill current buffer
-        self.fill_buffer(&mut curr_buffer);
+        Self::fill_buffer(&self.cells, &self.fading, &mut self.back_buffer);
 
-        let diff = self.buffer.diff(&curr_buffer);
-        self.buffer = curr_buffer;
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
         diff
     }
 
     fn update(&mut self) {
-        let mut next_cells = HashMap::new();
-
         // update current generation counter
         self.current_gen = (self.current_gen + 1) % 255;
 
-        for (index, _) in self.buffer.iter().enumerate() {
-            let neighbors = get_neighbors_by_index(&self.buffer, index);
-            if neighbors.is_empty() {
-                continue;
-            };
-            let (nx, ny) = self.buffer.pos_of(index);
-            let alive_neighbors = neighbors.len();
-
-            if let Some(cell) = self.cells.get_mut(&(nx, ny)) {
-                cell.update_color_and_char(&mut self.rng, self.current_gen);
+        let mut next_cells = self.next_generation();
 
-                // Survival: an alive cell with 2 or 3 alive neighbors stays alive
-                if alive_neighbors == 2 || alive_neighbors == 3 {
-                    next_cells.insert((nx, ny), cell.clone());
-                }
-            } else {
-                // Birth: a dead cell with exactly 3 alive neighbors becomes alive
-                if alive_neighbors == 3 {
-                    let mut new_cell = LifeCell::new('*');
-                    new_cell.update_color_and_char(&mut self.rng, self.current_gen); // Initialize generation and update color/char
-                    next_cells.insert((nx, ny), new_cell);
-                    // Replace 'X' with the desired initial state
-                }
-                // TODO:  here should process state of dead cell
-            };
-        }
-
-        // generate new cells, if cell already present, skip
-        for _ in 0..9 {
-            // Inserting glider at a random position with random rotation
-            let glider_size = 3;
-            let x = self.rng.gen_range(2..self.buffer.width - glider_size + 1);
-            let y = self.rng.gen_range(2..self.buffer.height - glider_size + 1);
-            let rotation = [0, 90, 180, 270][self.rng.gen_range(0..4)];
-            insert_glider(&mut next_cells, x, y, rotation, self.current_gen);
+        // generate new cells, if cell already present, skip, pattern is
+        // left to evolve on its own
+        let gliders = match self.options.seed_pattern {
+            Some(_) => 0,
+            None => 9,
+        };
+        for _ in 0..gliders {
+            self.insert_random_glider(&mut next_cells);
         }
+        self.fade_dead_cells(&next_cells);
         self.cells = next_cells;
     }
 
@@ -148,38 +178,185 @@ impl TerminalEffect for ConwayLife {
     }
 
     fn reset(&mut self) {
-        todo!();
+        let new_effect = Self::with_rng(self.options.clone(), self.rng.clone());
+        *self = new_effect;
+    }
+
+    /// Cells inside new screen keep evolving, the rest are gone
+    fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        let (width, height) = (width as usize, height as usize);
+        self.cells.retain(|&(x, y), _| x < width && y < height);
+        self.fading.retain(|&(x, y), _| x < width && y < height);
+        self.buffer = Buffer::new(width, height);
+        self.back_buffer = self.buffer.clone();
+    }
+
+    /// Space drops bunch of gliders to stir up settled field
+    fn on_key(&mut self, key: event::KeyEvent) {
+        if key.code == event::KeyCode::Char(' ') {
+            let mut cells = std::mem::take(&mut self.cells);
+            for _ in 0..GLIDERS_ON_KEY {
+                self.insert_random_glider(&mut cells);
+            }
+            self.cells = cells;
+        }
     }
 }
 
 impl ConwayLife {
+    /// Printed by `tarts life --help`
+    pub fn help() -> &'static str {
+        "life: Conway's Game of Life, random gliders are added every generation
+
+Options:
+  --pattern <file>  seed with plaintext or RLE pattern placed at the center
+                    instead of random cells
+  --seed <n>        seed random generator
+
+Keys:
+  space             drop a few gliders
+"
+    }
+
+    /// Insert glider at a random position with random rotation
+    fn insert_random_glider(
+        &mut self,
+        cells: &mut HashMap<(usize, usize), LifeCell>,
+    ) {
+        let glider_size = 3;
+        let x = self
+            .rng
+            .gen_range(0..=self.buffer.width.saturating_sub(glider_size));
+        let y = self
+            .rng
+            .gen_range(0..=self.buffer.height.saturating_sub(glider_size));
+        let rotation = [0, 90, 180, 270][self.rng.gen_range(0..4)];
+        insert_glider(cells, x, y, rotation, self.current_gen);
+        // glider is cut on screens smaller than it
+        let (width, height) = self.buffer.get_size();
+        cells.retain(|&(x, y), _| x < width && y < height);
+    }
+
     pub fn new(options: ConwayLifeOptions) -> Self {
-        let mut rng = rand::thread_rng();
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Create effect with provided random generator
+    pub fn with_rng(options: ConwayLifeOptions, mut rng: EffectRng) -> Self {
         let buffer = Buffer::new(
             options.screen_size.0 as usize,
             options.screen_size.1 as usize,
         );
 
         let mut cells = HashMap::new();
-        for _ in 0..options.initial_cells {
-            let lc = LifeCell::new('*');
-            let x = rng.gen_range(0..options.screen_size.0) as usize;
-            let y = rng.gen_range(0..options.screen_size.1) as usize;
+        match &options.seed_pattern {
+            Some(pattern) => {
+                // pattern is validated by builder
+                let pattern = parse_pattern(pattern).unwrap_or_default();
+                for position in pattern.centered(options.screen_size) {
+                    cells.insert(position, LifeCell::new('*'));
+                }
+            }
+            None => {
+                for _ in 0..options.initial_cells {
+                    let lc = LifeCell::new('*');
+                    let x = rng.gen_range(0..options.screen_size.0) as usize;
+                    let y = rng.gen_range(0..options.screen_size.1) as usize;
 
-            cells.insert((x, y), lc);
+                    cells.insert((x, y), lc);
+                }
+            }
         }
 
         Self {
             options,
+            back_buffer: buffer.clone(),
             buffer,
             cells,
+            fading: HashMap::new(),
             rng,
             current_gen: 0,
         }
     }
 
-    pub fn fill_buffer(&mut self, buffer: &mut Buffer) {
-        for ((x, y), cell) in self.cells.iter() {
+    /// Apply life rules to the cells drawn in buffer
+    pub fn next_generation(&mut self) -> HashMap<(usize, usize), LifeCell> {
+        let mut next_cells = HashMap::new();
+
+        for (index, _) in self.buffer.iter().enumerate() {
+            let neighbors = if self.options.wrap_edges {
+                get_neighbors_by_index_wrapped(&self.buffer, index)
+            } else {
+                get_neighbors_by_index(&self.buffer, index)
+            };
+            // fading cells are drawn as well, but they are dead
+            let alive_neighbors = neighbors
+                .iter()
+                .filter(|(index, _)| {
+                    !self.fading.contains_key(&self.buffer.pos_of(*index))
+                })
+                .count();
+            if alive_neighbors == 0 {
+                continue;
+            };
+            let (nx, ny) = self.buffer.pos_of(index);
+
+            if let Some(cell) = self.cells.get_mut(&(nx, ny)) {
+                cell.update_color_and_char(&mut self.rng, self.current_gen);
+
+                // Survival: an alive cell with 2 or 3 alive neighbors stays alive
+                if alive_neighbors == 2 || alive_neighbors == 3 {
+                    next_cells.insert((nx, ny), cell.clone());
+                }
+            } else {
+                // Birth: a dead cell with exactly 3 alive neighbors becomes alive
+                if alive_neighbors == 3 {
+                    let mut new_cell = LifeCell::new('*');
+                    new_cell.update_color_and_char(&mut self.rng, self.current_gen); // Initialize generation and update color/char
+                    next_cells.insert((nx, ny), new_cell);
+                    // Replace 'X' with the desired initial state
+                }
+                // TODO:  here should process state of dead cell
+            };
+        }
+
+        next_cells
+    }
+
+    /// Dim fading cells, start fading of cells missing in the next generation
+    fn fade_dead_cells(&mut self, next_cells: &HashMap<(usize, usize), LifeCell>) {
+        self.fading.retain(|position, cell| {
+            !next_cells.contains_key(position) && cell.fade()
+        });
+        if self.options.fade_frames == 0 {
+            return;
+        }
+        for (position, cell) in self.cells.iter() {
+            if !next_cells.contains_key(position) {
+                let mut dead = cell.clone();
+                dead.health = self.options.fade_frames;
+                dead.fade();
+                self.fading.insert(*position, dead);
+            }
+        }
+    }
+
+    /// Draw fading cells first, so live ones are drawn over them
+    pub fn fill_buffer(
+        cells: &HashMap<(usize, usize), LifeCell>,
+        fading: &HashMap<(usize, usize), LifeCell>,
+        buffer: &mut Buffer,
+    ) {
+        for ((x, y), cell) in fading.iter() {
+            buffer.set(
+                *x,
+                *y,
+                Cell::new(cell.character, cell.color, style::Attribute::Reset),
+            )
+        }
+        for ((x, y), cell) in cells.iter() {
             buffer.set(
                 *x,
                 *y,
@@ -198,13 +375,14 @@ fn insert_glider(
 ) {
     let base_glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
 
+    // rotate inside of 3x3 box with top left corner at (x, y)
     let rotated_glider = base_glider.iter().map(|&(dx, dy)| {
         match rotation {
             0 => (x + dx, y + dy),
-            90 => (x + dy, y - dx + 2), // Adjusted for rotation
-            180 => (x - dx + 2, y - dy + 2), // Adjusted for rotation
-            270 => (x - dy, y + dx),    // Adjusted for rotation
-            _ => (x + dx, y + dy),      // Default case, no rotation
+            90 => (x + 2 - dy, y + dx),
+            180 => (x + 2 - dx, y + 2 - dy),
+            270 => (x + dy, y + 2 - dx),
+            _ => (x + dx, y + dy), // Default case, no rotation
         }
     });
 
@@ -220,6 +398,7 @@ fn insert_glider(
                     g: green_color,
                     b: 0,
                 },
+                health: 0,
             },
         );
     }
@@ -249,7 +428,37 @@ pub fn get_neighbors_by_index(buf: &Buffer, index: usize) -> Vec<(usize, Cell)>
     neighbors
 }
 
-#[allow(dead_code)]
+/// Same as `get_neighbors_by_index` but edges of buffer are wrapped around
+pub fn get_neighbors_by_index_wrapped(
+    buf: &Buffer,
+    index: usize,
+) -> Vec<(usize, Cell)> {
+    // on buffers narrower than 3 cells offsets wrap onto each other, each
+    // neighbor should be counted once
+    let offsets = |size: usize| {
+        let mut offsets = vec![size - 1, 0, 1 % size];
+        offsets.sort_unstable();
+        offsets.dedup();
+        offsets
+    };
+    let mut neighbors = Vec::new();
+    let (x, y) = buf.pos_of(index);
+    for i in offsets(buf.width) {
+        for j in offsets(buf.height) {
+            if i == 0 && j == 0 {
+                continue; // Skip the cell itself
+            }
+            let nx = (x + i) % buf.width;
+            let ny = (y + j) % buf.height;
+            let cell = buf.get(nx, ny);
+            if cell.symbol != ' ' {
+                neighbors.push((buf.index_of(nx, ny), cell));
+            }
+        }
+    }
+    neighbors
+}
+
 pub fn get_neighbors_by_coords(
     buf: &Buffer,
     x: usize,
@@ -281,6 +490,26 @@ pub fn get_neighbors_by_coords(
 mod tests {
     use super::*;
 
+    #[test]
+    fn space_drops_gliders() {
+        let options = ConwayLifeOptionsBuilder::default()
+            .screen_size((40, 20))
+            .seed(Some(3))
+            .build()
+            .unwrap();
+        let mut life = ConwayLife::new(options);
+        life.cells.clear();
+
+        let key = |c| {
+            event::KeyEvent::new(event::KeyCode::Char(c), event::KeyModifiers::NONE)
+        };
+        life.on_key(key('x'));
+        assert!(life.cells.is_empty());
+        life.on_key(key(' '));
+        assert!(!life.cells.is_empty());
+        assert!(life.cells.len() <= GLIDERS_ON_KEY * 5);
+    }
+
     #[test]
     fn empty_neighbors_by_index() {
         let buf = Buffer::new(3, 3);
@@ -307,4 +536,190 @@ mod tests {
         let res = get_neighbors_by_index(&buf, buf.index_of(0, 0));
         assert_eq!(res.len(), 1);
     }
+
+    #[test]
+    fn wrapped_neighbors_by_index() {
+        let mut buf = Buffer::new(4, 4);
+        let cell = Cell::new('*', style::Color::Blue, style::Attribute::Bold);
+        buf.set(3, 3, cell);
+        buf.set(3, 0, cell);
+        buf.set(0, 3, cell);
+
+        let res = get_neighbors_by_index(&buf, buf.index_of(0, 0));
+        assert!(res.is_empty());
+
+        let res = get_neighbors_by_index_wrapped(&buf, buf.index_of(0, 0));
+        assert_eq!(res.len(), 3);
+    }
+
+    #[test]
+    fn wrapped_neighbors_counted_once_on_narrow_buffer() {
+        let mut buf = Buffer::new(2, 5);
+        let cell = Cell::new('*', style::Color::Blue, style::Attribute::Bold);
+        for y in 0..5 {
+            buf.set(0, y, cell);
+            buf.set(1, y, cell);
+        }
+
+        // left and right neighbor is the same cell, and so are diagonals
+        let res = get_neighbors_by_index_wrapped(&buf, buf.index_of(0, 2));
+        assert_eq!(res.len(), 5);
+        let mut indexes: Vec<usize> = res.iter().map(|(index, _)| *index).collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        assert_eq!(indexes.len(), 5);
+    }
+
+    #[test]
+    fn glider_wraps_around_edges() {
+        let options = ConwayLifeOptionsBuilder::default()
+            .screen_size((10, 10))
+            .initial_cells(0_u32)
+            .wrap_edges(true)
+            .build()
+            .unwrap();
+        let mut life = ConwayLife::new(options);
+        insert_glider(&mut life.cells, 7, 3, 0, 0);
+
+        // glider moves one cell right and down each 4 generations
+        for _ in 0..16 {
+            let _ = life.get_diff();
+            life.cells = life.next_generation();
+        }
+        assert_eq!(life.cells.len(), 5);
+        assert!(life.cells.keys().all(|&(x, _)| x < 4));
+    }
+
+    #[test]
+    fn same_rng_same_frames() {
+        let options = ConwayLifeOptionsBuilder::default()
+            .screen_size((40, 20))
+            .initial_cells(200_u32)
+            .build()
+            .unwrap();
+        let mut first = ConwayLife::with_rng(options.clone(), make_rng(Some(3)));
+        let mut second = ConwayLife::with_rng(options, make_rng(Some(3)));
+        for _ in 0..10 {
+            assert_eq!(first.get_diff(), second.get_diff());
+            first.update();
+            second.update();
+        }
+    }
+
+    #[test]
+    fn gliders_cut_on_tiny_screen() {
+        let options = ConwayLifeOptionsBuilder::default()
+            .screen_size((2, 2))
+            .initial_cells(0_u32)
+            .seed(Some(1))
+            .build()
+            .unwrap();
+        let mut life = ConwayLife::new(options);
+        for _ in 0..10 {
+            life.update();
+            life.get_diff();
+        }
+        assert!(life.cells.keys().all(|&(x, y)| x < 2 && y < 2));
+    }
+
+    #[test]
+    fn reset_after_resize() {
+        let options = ConwayLifeOptionsBuilder::default()
+            .screen_size((40, 20))
+            .build()
+            .unwrap();
+        let mut life = ConwayLife::new(options);
+        life.update_size(20, 10);
+        life.reset();
+        assert_eq!(life.buffer.get_size(), (20, 10));
+        assert!(life.cells.keys().all(|&(x, y)| x < 20 && y < 10));
+    }
+
+    #[test]
+    fn glider_fits_box_at_origin() {
+        for rotation in [0, 90, 180, 270] {
+            let mut cells = HashMap::new();
+            insert_glider(&mut cells, 0, 0, rotation, 0);
+            assert_eq!(cells.len(), 5);
+            assert!(cells.keys().all(|&(x, y)| x < 3 && y < 3));
+        }
+    }
+
+    #[test]
+    fn seeded_from_pattern() {
+        let options = ConwayLifeOptionsBuilder::default()
+            .screen_size((10, 10))
+            .seed_pattern(Some("OOO".to_string()))
+            .build()
+            .unwrap();
+        let mut life = ConwayLife::new(options);
+        let mut positions: Vec<_> = life.cells.keys().copied().collect();
+        positions.sort();
+        assert_eq!(positions, vec![(3, 4), (4, 4), (5, 4)]);
+
+        // blinker flips to vertical, nothing else added
+        let _ = life.get_diff();
+        life.update();
+        let mut positions: Vec<_> = life.cells.keys().copied().collect();
+        positions.sort();
+        assert_eq!(positions, vec![(4, 3), (4, 4), (4, 5)]);
+
+        let invalid = ConwayLifeOptionsBuilder::default()
+            .screen_size((10, 10))
+            .seed_pattern(Some("O?O".to_string()))
+            .build();
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn dead_cells_fade_out() {
+        let options = ConwayLifeOptionsBuilder::default()
+            .screen_size((10, 10))
+            .initial_cells(0_u32)
+            .fade_frames(3_u8)
+            .build()
+            .unwrap();
+        let mut life = ConwayLife::new(options);
+        life.cells.insert((5, 5), LifeCell::new('*'));
+        let _ = life.get_diff();
+
+        // lonely cell dies and fades through darker greens
+        let next_cells = life.next_generation();
+        life.fade_dead_cells(&next_cells);
+        life.cells = next_cells;
+        let mut greens = vec![];
+        while let Some(cell) = life.fading.get(&(5, 5)) {
+            if let style::Color::Rgb { g, .. } = cell.color {
+                greens.push(g);
+            }
+            life.fade_dead_cells(&HashMap::new());
+        }
+        assert_eq!(greens, vec![191, 127, 63]);
+
+        // fading cell is not counted as alive neighbor
+        let _ = life.get_diff();
+        life.fading.insert((4, 4), LifeCell::new('*'));
+        life.fading.insert((4, 5), LifeCell::new('*'));
+        life.fading.insert((4, 6), LifeCell::new('*'));
+        let _ = life.get_diff();
+        assert!(life.next_generation().is_empty());
+    }
+
+    #[test]
+    fn double_buffer_same_diff_as_fresh_buffer() {
+        let options = ConwayLifeOptionsBuilder::default()
+            .screen_size((30, 20))
+            .initial_cells(200_u32)
+            .seed(Some(5))
+            .build()
+            .unwrap();
+        let mut life = ConwayLife::new(options);
+        for _ in 0..30 {
+            let previous = life.buffer.clone();
+            let mut fresh = Buffer::new(30, 20);
+            ConwayLife::fill_buffer(&life.cells, &life.fading, &mut fresh);
+            assert_eq!(life.get_diff(), previous.diff(&fresh));
+            life.update();
+        }
+    }
 }
@@ -1,3 +1,4 @@
 pub mod conway_life;
-#[allow(unused)]
+pub mod pattern;
+pub mod rle;
 pub use conway_life::{ConwayLife, ConwayLifeOptions, ConwayLifeOptionsBuilder};
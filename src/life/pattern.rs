@@ -0,0 +1,111 @@
+//! Life patterns written as text. Plaintext one is a grid of `.` for dead
+//! and `O` (or `*`) for live cells, lines starting with `!` are comments.
+//! RLE format is parsed in `rle` module.
+use crate::life::rle::parse_rle;
+
+/// Size of the pattern and coordinates of its live cells
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl Pattern {
+    pub fn from_cells(cells: Vec<(usize, usize)>) -> Self {
+        Self {
+            width: cells.iter().map(|(x, _)| x + 1).max().unwrap_or(0),
+            height: cells.iter().map(|(_, y)| y + 1).max().unwrap_or(0),
+            cells,
+        }
+    }
+
+    /// Live cells moved to the center of the screen, ones out of it dropped
+    pub fn centered(&self, screen_size: (u16, u16)) -> Vec<(usize, usize)> {
+        let (width, height) = (screen_size.0 as isize, screen_size.1 as isize);
+        let left = (width - self.width as isize) / 2;
+        let top = (height - self.height as isize) / 2;
+        self.cells
+            .iter()
+            .map(|(x, y)| (*x as isize + left, *y as isize + top))
+            .filter(|(x, y)| (0..width).contains(x) && (0..height).contains(y))
+            .map(|(x, y)| (x as usize, y as usize))
+            .collect()
+    }
+}
+
+/// Parse pattern in plaintext or RLE format, RLE is told by its header
+pub fn parse_pattern(text: &str) -> Result<Pattern, String> {
+    let is_rle = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .is_some_and(|line| line.starts_with('x'));
+    match is_rle {
+        true => parse_rle(text),
+        false => parse_plaintext(text),
+    }
+}
+
+pub fn parse_plaintext(text: &str) -> Result<Pattern, String> {
+    let mut cells = vec![];
+    let rows = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('!'));
+    for (y, row) in rows.enumerate() {
+        for (x, symbol) in row.chars().enumerate() {
+            match symbol {
+                'O' | '*' => cells.push((x, y)),
+                '.' => {}
+                _ => {
+                    return Err(format!(
+                        "unexpected '{}' at line {} of plaintext pattern",
+                        symbol,
+                        y + 1
+                    ))
+                }
+            }
+        }
+    }
+    if cells.is_empty() {
+        return Err("pattern has no live cells".to_string());
+    }
+    Ok(Pattern::from_cells(cells))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glider_in_both_formats() {
+        let plaintext = "!Name: Glider\n.O.\n..O\nOOO\n";
+        let rle = "#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+        let expected = Pattern {
+            width: 3,
+            height: 3,
+            cells: vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)],
+        };
+        assert_eq!(parse_pattern(plaintext), Ok(expected.clone()));
+        assert_eq!(parse_pattern(rle), Ok(expected));
+    }
+
+    #[test]
+    fn invalid_patterns_rejected() {
+        assert!(parse_pattern(".O.\n.X.").is_err());
+        assert!(parse_pattern("...").is_err());
+        assert!(parse_pattern("x = 3, y = 1\n3q!").is_err());
+    }
+
+    #[test]
+    fn centered_on_screen() {
+        let pattern = parse_pattern("OO\nOO").unwrap();
+        assert_eq!(
+            pattern.centered((6, 4)),
+            vec![(2, 1), (3, 1), (2, 2), (3, 2)]
+        );
+        // pattern bigger than screen is clipped
+        assert_eq!(pattern.centered((1, 1)), vec![(0, 0)]);
+    }
+}
@@ -0,0 +1,152 @@
+//! Run Length Encoded patterns, format used by most Life pattern files:
+//!
+//! ```text
+//! #N Glider
+//! x = 3, y = 3, rule = B3/S23
+//! bo$2bo$3o!
+//! ```
+//!
+//! Lines starting with `#` are comments, header gives size of the pattern,
+//! body has runs of `b` (dead) and `o` (live) cells, optionally prefixed
+//! with count, `$` ends the row and `!` ends the pattern.
+use crate::life::pattern::Pattern;
+
+/// Parse `x = .., y = ..` header into pattern width and height
+fn parse_header(line: &str) -> Result<(usize, usize), String> {
+    let mut size = (None, None);
+    for item in line.split(',') {
+        let Some((key, value)) = item.split_once('=') else {
+            return Err(format!("malformed RLE header item '{}'", item.trim()));
+        };
+        let value = value.trim();
+        let parse = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("invalid size '{}' in RLE header", value))
+        };
+        match key.trim() {
+            "x" => size.0 = Some(parse()?),
+            "y" => size.1 = Some(parse()?),
+            _ => {}
+        }
+    }
+    match size {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err("RLE header should have both x and y".to_string()),
+    }
+}
+
+pub fn parse_rle(text: &str) -> Result<Pattern, String> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let (width, height) = match lines.next() {
+        Some(header) if header.starts_with('x') => parse_header(header)?,
+        _ => {
+            return Err("RLE pattern should start with x = .., y = .. header".into())
+        }
+    };
+
+    let mut cells = vec![];
+    let (mut x, mut y) = (0_usize, 0_usize);
+    let mut run = String::new();
+    let mut terminated = false;
+
+    'lines: for line in lines {
+        for symbol in line.chars() {
+            if symbol.is_ascii_digit() {
+                run.push(symbol);
+                continue;
+            }
+            let count = match run.is_empty() {
+                true => 1,
+                false => run
+                    .parse::<usize>()
+                    .map_err(|_| format!("run length {} is too big", run))?,
+            };
+            run.clear();
+            match symbol {
+                // run is checked before cells are added, so huge counts
+                // don't eat all the memory
+                'o' | 'b' => {
+                    let end = x
+                        .checked_add(count)
+                        .filter(|end| *end <= width)
+                        .ok_or_else(|| {
+                            format!("row {} is wider than x = {}", y + 1, width)
+                        })?;
+                    if symbol == 'o' {
+                        cells.extend((x..end).map(|x| (x, y)));
+                    }
+                    x = end;
+                }
+                '$' => {
+                    y = y.checked_add(count).filter(|y| *y <= height).ok_or_else(
+                        || format!("pattern is higher than y = {}", height),
+                    )?;
+                    x = 0;
+                }
+                '!' => {
+                    terminated = true;
+                    break 'lines;
+                }
+                symbol if symbol.is_whitespace() => {}
+                _ => return Err(format!("unexpected '{}' in RLE pattern", symbol)),
+            }
+        }
+    }
+
+    if !terminated {
+        return Err("RLE pattern should end with !".to_string());
+    }
+    if cells.iter().any(|(_, y)| *y >= height) {
+        return Err(format!("pattern is higher than y = {}", height));
+    }
+    if cells.is_empty() {
+        return Err("pattern has no live cells".to_string());
+    }
+    Ok(Pattern {
+        width,
+        height,
+        cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glider_gun_parsed() {
+        let gun = "#N Gosper glider gun
+x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!";
+        let pattern = parse_rle(gun).unwrap();
+        assert_eq!((pattern.width, pattern.height), (36, 9));
+        assert_eq!(pattern.cells.len(), 36);
+        assert_eq!(pattern.cells[0], (24, 0));
+        assert_eq!(pattern.cells.last(), Some(&(13, 8)));
+    }
+
+    #[test]
+    fn malformed_files_rejected() {
+        let errors = [
+            ("bo$2bo$3o!", "header"),
+            ("x = 3\n3o!", "both x and y"),
+            ("x = a, y = 1\n3o!", "invalid size"),
+            ("x = 3, y = 1\n3o", "end with !"),
+            ("x = 2, y = 1\n3o!", "wider"),
+            ("x = 3, y = 1\n3o$o!", "higher"),
+            ("x = 3, y = 1\n3q!", "unexpected 'q'"),
+            ("x = 3, y = 1\n99999999999999o!", "wider"),
+            ("x = 3, y = 1\n2o18446744073709551615b!", "wider"),
+            ("x = 3, y = 2\no18446744073709551615$o!", "higher"),
+        ];
+        for (text, error) in errors {
+            let message = parse_rle(text).unwrap_err();
+            assert!(message.contains(error), "{}: {}", text, message);
+        }
+    }
+}
@@ -20,6 +20,16 @@
 //! tarts matrix
 //! tarts life
 //! tarts maze
+//! tarts scope
+//! tarts brain
+//! tarts wator
+//! tarts sand
+//! tarts ant
+//! tarts eca --rule 110
+//! tarts mandel
+//! tarts globe
+//! tarts clock
+//! tarts --cycle matrix,life,globe --interval 30
 //! ```
 //!
 //! ## Installation
@@ -45,18 +55,75 @@
 //! This project is licensed under the [MIT License](https://opensource.org/licenses/MIT).
 //!
 #![cfg(not(test))]
-use crossterm::{self, cursor, execute, terminal};
-use std::{io, process};
+use crossterm::{self, style, terminal};
+use std::{
+    env,
+    fs::{self, File},
+    io,
+    path::PathBuf,
+    process,
+    time::Duration,
+};
+use tarts::{
+    ant, audio, blank, brain, check, clock, color, common, cycle, eca, globe, life,
+    mandel, maze, rain, record, sand, scope, wator,
+};
 
-mod blank;
-mod buffer;
-mod check;
-mod common;
-mod life;
-mod maze;
-mod rain;
+const HELP: &str = "Terminal screensavers, run with effect name as arg:
+  matrix, life, maze, scope, brain, wator, sand, ant, eca, mandel, globe,
+  clock, blank
 
-const HELP: &str = "Terminal screensavers, run with arg: matrix, life, maze";
+Options of the effect are shown with `tarts <effect> --help`.
+
+Options:
+  --seed <n>        seed random generator to get reproducible effect,
+                    without it every run is different
+  --no-alt-screen   draw on normal screen instead of alternate one,
+                    frames are kept in scrollback (for debugging)
+  --color-mode <m>  true, 256 or 16 colors for terminals without truecolor
+                    support, detected from COLORTERM and TERM if not set
+  --record <file>   write effect to animated GIF instead of terminal,
+                    --frames <n> sets its length (120 by default)
+  --record-cast <f> write asciinema v2 cast instead of terminal, length
+                    is set with --frames as well
+  --bg <color>      paint background with color, #RRGGBB, rgb(r,g,b) or
+                    name like white, for terminals with light theme
+  --invert          swap foreground and background of the whole screen,
+                    like matrix on white
+  --pan             slowly move the whole frame around, so static effects
+                    like clock don't burn into OLED or plasma screens
+  --once            draw single frame on normal screen and exit, frame
+                    stays visible, handy for previews
+  --stats-json      print fps, frames, effect and terminal size as JSON
+                    after the run instead of plain fps
+  --cycle <list>    play comma separated effects one after another, like
+                    --cycle matrix,life,globe, effect name is not needed,
+                    any key quits
+  --interval <secs> how long each effect of --cycle is played, 30 by default
+  --lock <word>     exit only after the word is typed, q, Esc, Ctrl-C
+                    are ignored, without it they quit
+  --idle <secs>     keep screen blank until there is no input for given
+                    seconds, then run effect until any key is pressed
+  --pattern <file>  seed life with plaintext or RLE pattern file placed
+                    at the center instead of random cells
+  --density <d>     low, medium or high number of matrix drops,
+                    medium by default
+  --hue <mode>      green or rainbow colors of matrix, rainbow changes
+                    hue across columns
+  --glitch          matrix randomly corrupts blocks of the screen
+  --source <file>   matrix drops spell text of the file, like source code,
+                    instead of random characters
+  --12h             clock shows hours 1..12 with AM or PM
+  --no-seconds      clock shows only hours and minutes
+  --rule <n>        rule number 0..255 of eca effect, 110 by default
+  --pause <frames>  how long finished maze stays on screen, 120 by default
+  --steps <n>       carving steps per frame of maze, 1 by default, more
+                    finish sooner on large terminals
+  --algorithm <a>   backtracker, prim or kruskal maze, backtracker by default
+  --style <s>       glitch or box drawing walls of maze, glitch by default
+  --reactive        effects react to loudness of default audio input,
+                    needs build with `audio` feature
+";
 
 #[derive(Debug)]
 struct AppArgs {
@@ -64,6 +131,33 @@ struct AppArgs {
     check: bool,
     effect: Option<String>,
     frames: Option<usize>,
+    seed: Option<u64>,
+    no_alt_screen: bool,
+    color_mode: Option<color::ColorMode>,
+    record: Option<PathBuf>,
+    record_cast: Option<PathBuf>,
+    rule: Option<u16>,
+    lock: Option<String>,
+    idle: Option<u64>,
+    pattern: Option<PathBuf>,
+    reactive: bool,
+    density: Option<rain::digital_rain::Density>,
+    hue: Option<rain::digital_rain::HueMode>,
+    glitch: bool,
+    source: Option<PathBuf>,
+    twelve_hour: bool,
+    no_seconds: bool,
+    once: bool,
+    stats_json: bool,
+    cycle: Option<Vec<String>>,
+    interval: Option<u64>,
+    bg: Option<style::Color>,
+    invert: bool,
+    pan: bool,
+    pause: Option<u32>,
+    steps: Option<u32>,
+    algorithm: Option<maze::Algorithm>,
+    style: Option<maze::RenderStyle>,
 }
 
 fn main() -> std::io::Result<()> {
@@ -80,92 +174,426 @@ fn main() -> std::io::Result<()> {
     if args.check {
         let effect = args.effect.unwrap_or_else(|| "matrix".to_string());
         let frames = args.frames.unwrap_or(1);
-        return check::run_check(&effect, frames);
+        return check::run_check(&effect, frames, args.seed);
     }
 
-    let mut stdout = io::stdout();
+    let (width, height) = terminal::size()?;
+    if width == 0 || height == 0 {
+        eprintln!("Terminal size is {}x{}, nothing to draw on", width, height);
+        process::exit(1);
+    }
 
-    terminal::enable_raw_mode()?;
-    execute!(
-        stdout,
-        terminal::EnterAlternateScreen,
-        cursor::Hide,
-        terminal::Clear(terminal::ClearType::All)
-    )?;
+    // cast is recorded from regular loop, so it has to stop by itself
+    let iterations = args
+        .record_cast
+        .as_ref()
+        .map(|_| args.frames.unwrap_or(120));
+    let audio_level = args.reactive.then(audio::AudioLevel::new);
+    // capture stops when stream is dropped, so it's kept till exit
+    let _capture = audio_level.as_ref().map(start_capture);
+    let loop_options = common::LoopOptionsBuilder::default()
+        .iterations(iterations)
+        .color_mode(args.color_mode.unwrap_or_else(color::detect_color_support))
+        .lock(args.lock.clone())
+        // nobody is there to wait for when recording
+        .idle(
+            args.idle
+                .filter(|_| args.record_cast.is_none())
+                .map(Duration::from_secs),
+        )
+        .background(args.bg)
+        .invert(args.invert)
+        .pan(args.pan)
+        .any_key_quits(args.cycle.is_some())
+        .audio_level(audio_level)
+        .build()
+        .unwrap();
 
-    let (width, height) = terminal::size()?;
+    let stats = match &args.cycle {
+        Some(names) => {
+            let effects = names
+                .iter()
+                .map(|name| match build_effect(name, &args, (width, height))? {
+                    Some(effect) => Ok(effect),
+                    None => {
+                        eprintln!(
+                            "Unknown effect {} in --cycle, pick from [{}]",
+                            name,
+                            common::VALID_SAVERS.join(", ")
+                        );
+                        process::exit(1);
+                    }
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            let options = cycle::CycleOptionsBuilder::default()
+                .screen_size((width, height))
+                .interval(Duration::from_secs(args.interval.unwrap_or(30)))
+                .build()
+                .unwrap();
+            let mut cycle = cycle::Cycle::new(options, effects);
+            run_effect(&mut cycle, &args, &loop_options)?
+        }
+        None => match build_effect(&args.screen_saver, &args, (width, height))? {
+            Some(mut effect) => run_effect(&mut effect, &args, &loop_options)?,
+            None => {
+                println!("Pick screensaver: [{}]", common::VALID_SAVERS.join(", "));
+                return Ok(());
+            }
+        },
+    };
+
+    if let Some(stats) = stats {
+        if args.stats_json {
+            println!("{}", serde_json::to_string(&stats)?);
+        } else {
+            println!("Frames per second: {}", stats.fps);
+        }
+    }
+    Ok(())
+}
 
-    let fps = match args.screen_saver.as_str() {
+/// Create effect by its name for given screen size, `None` if name is unknown
+fn build_effect(
+    name: &str,
+    args: &AppArgs,
+    size: (u16, u16),
+) -> io::Result<Option<Box<dyn common::TerminalEffect>>> {
+    let effect: Box<dyn common::TerminalEffect> = match name {
         "matrix" => {
+            let source = match &args.source {
+                Some(path) => {
+                    let text =
+                        String::from_utf8_lossy(&fs::read(path)?).into_owned();
+                    let source = rain::digital_rain::SourceText::new(&text);
+                    if source.is_none() {
+                        eprintln!("Error: {} has no characters", path.display());
+                        process::exit(1);
+                    }
+                    source
+                }
+                None => None,
+            };
             let options = rain::digital_rain::DigitalRainOptionsBuilder::default()
-                .screen_size((width, height))
-                .drops_range((120, 240))
+                .screen_size(size)
+                .drops_range(
+                    args.density.unwrap_or_default().drops_range((120, 240)),
+                )
                 .speed_range((2, 16))
+                .hue_mode(args.hue.unwrap_or_default())
+                .glitch_rate(match args.glitch {
+                    true => rain::digital_rain::GLITCH_RATE,
+                    false => 0.0,
+                })
+                .source(source)
+                .seed(args.seed)
                 .build()
                 .unwrap();
-            let mut digital_rain = rain::digital_rain::DigitalRain::new(options);
-            common::run_loop(&mut stdout, &mut digital_rain, None)?
+            Box::new(rain::digital_rain::DigitalRain::new(options))
         }
         "life" => {
+            let seed_pattern = match &args.pattern {
+                Some(path) => Some(fs::read_to_string(path)?),
+                None => None,
+            };
             let options = life::ConwayLifeOptionsBuilder::default()
-                .screen_size((width, height))
+                .screen_size(size)
+                .seed(args.seed)
+                .seed_pattern(seed_pattern)
                 .build()
-                .unwrap();
-            let mut conway_life = life::ConwayLife::new(options);
-            common::run_loop(&mut stdout, &mut conway_life, None)?
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                });
+            Box::new(life::ConwayLife::new(options))
         }
         "maze" => {
             let options = maze::MazeOptionsBuilder::default()
-                .screen_size((width, height))
+                .screen_size(size)
+                .seed(args.seed)
+                .show_solution(true)
+                .complete_pause(args.pause.unwrap_or(maze::COMPLETE_PAUSE))
+                .steps_per_frame(args.steps.unwrap_or(1))
+                .algorithm(args.algorithm.unwrap_or_default())
+                .render_style(args.style.unwrap_or_default())
+                .build()
+                .unwrap();
+            Box::new(maze::Maze::new(options))
+        }
+        "scope" => {
+            let options = scope::ScopeOptionsBuilder::default()
+                .screen_size(size)
+                .build()
+                .unwrap();
+            Box::new(scope::Scope::new(options))
+        }
+        "brain" => {
+            let options = brain::BrainOptionsBuilder::default()
+                .screen_size(size)
+                .seed(args.seed)
+                .build()
+                .unwrap();
+            Box::new(brain::Brain::new(options))
+        }
+        "wator" => {
+            let options = wator::WatorOptionsBuilder::default()
+                .screen_size(size)
+                .seed(args.seed)
+                .build()
+                .unwrap();
+            Box::new(wator::Wator::new(options))
+        }
+        "sand" => {
+            let options = sand::SandOptionsBuilder::default()
+                .screen_size(size)
+                .seed(args.seed)
+                .build()
+                .unwrap();
+            Box::new(sand::Sand::new(options))
+        }
+        "ant" => {
+            let options = ant::AntOptionsBuilder::default()
+                .screen_size(size)
+                .seed(args.seed)
                 .build()
                 .unwrap();
-            let mut maze = maze::Maze::new(options);
-            common::run_loop(&mut stdout, &mut maze, None)?
+            Box::new(ant::Langton::new(options))
+        }
+        "eca" => {
+            let options = eca::EcaOptionsBuilder::default()
+                .screen_size(size)
+                .rule(args.rule.unwrap_or(110))
+                .seed(args.seed)
+                .build()
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                });
+            Box::new(eca::Eca::new(options))
+        }
+        "mandel" => {
+            let options = mandel::MandelOptionsBuilder::default()
+                .screen_size(size)
+                .build()
+                .unwrap();
+            Box::new(mandel::Mandel::new(options))
+        }
+        "globe" => {
+            let options = globe::GlobeOptionsBuilder::default()
+                .screen_size(size)
+                .build()
+                .unwrap();
+            Box::new(globe::Globe::new(options))
+        }
+        "clock" => {
+            let options = clock::ClockOptionsBuilder::default()
+                .screen_size(size)
+                .twelve_hour(args.twelve_hour)
+                .show_seconds(!args.no_seconds)
+                .seed(args.seed)
+                .build()
+                .unwrap();
+            Box::new(clock::Clock::new(options))
         }
         "blank" => {
             let options = blank::BlankOptionsBuilder::default()
-                .screen_size((width, height))
+                .screen_size(size)
                 .build()
                 .unwrap();
-            let mut check = blank::Blank::new(options);
-            common::run_loop(&mut stdout, &mut check, None)?
+            Box::new(blank::Blank::new(options))
         }
 
-        _ => {
-            println!("Pick screensaver: [matrix, life, maze]");
-            0.0
-        }
+        _ => return Ok(None),
     };
+    Ok(Some(effect))
+}
 
-    execute!(
-        stdout,
-        cursor::Show,
-        terminal::Clear(terminal::ClearType::All),
-        terminal::LeaveAlternateScreen,
-    )?;
-    terminal::disable_raw_mode()?;
+/// Help of the effect, `HELP` lists options shared by all of them
+fn effect_help(name: &str) -> &'static str {
+    match name {
+        "matrix" => rain::digital_rain::DigitalRain::help(),
+        "life" => life::ConwayLife::help(),
+        "maze" => maze::Maze::help(),
+        "scope" => scope::Scope::help(),
+        "brain" => brain::Brain::help(),
+        "wator" => wator::Wator::help(),
+        "sand" => sand::Sand::help(),
+        "ant" => ant::Langton::help(),
+        "eca" => eca::Eca::help(),
+        "mandel" => mandel::Mandel::help(),
+        "globe" => globe::Globe::help(),
+        "clock" => clock::Clock::help(),
+        "blank" => blank::Blank::help(),
+        _ => HELP,
+    }
+}
 
-    println!("Frames per second: {}", fps);
+/// Run effect on alternate screen or record it to file, exit with message
+/// if terminal is smaller than effect needs. Returns stats of the run
+fn run_effect<TE: common::TerminalEffect>(
+    effect: &mut TE,
+    args: &AppArgs,
+    loop_options: &common::LoopOptions,
+) -> io::Result<Option<common::RunStats>> {
+    let (width, height) = terminal::size()?;
+    let (min_width, min_height) = effect.min_size();
+    if width < min_width || height < min_height {
+        eprintln!(
+            "Terminal is too small for {}: {}x{}, need at least {}x{}",
+            effect.name(),
+            width,
+            height,
+            min_width,
+            min_height
+        );
+        process::exit(1);
+    }
+
+    if let Some(path) = &args.record {
+        let frames = args.frames.unwrap_or(120);
+        record::record_gif(
+            effect,
+            (width, height),
+            frames,
+            path,
+            &record::RecordOptions::default(),
+        )?;
+        println!("Recorded {} frames to {}", frames, path.display());
+        return Ok(None);
+    }
+
+    if let Some(path) = &args.record_cast {
+        let mut cast = record::CastWriter::new(
+            File::create(path)?,
+            (width, height),
+            Duration::from_secs_f64(1.0 / common::TARGET_FPS),
+        )?;
+        common::run_loop_with_options(&mut cast, effect, loop_options)?;
+        println!("Recorded cast to {}", path.display());
+        return Ok(None);
+    }
+
+    if args.once {
+        render_once(effect, args)?;
+        return Ok(None);
+    }
+
+    let _guard = common::TerminalGuard::new(!args.no_alt_screen)?;
+    common::run_loop_with_options(&mut io::stdout(), effect, loop_options).map(Some)
+}
+
+/// Draw complete frame over normal screen and leave it there, cursor is
+/// put below so shell prompt doesn't overwrite the frame
+fn render_once<TE: common::TerminalEffect>(
+    effect: &mut TE,
+    args: &AppArgs,
+) -> io::Result<()> {
+    let color_mode = args.color_mode.unwrap_or_else(color::detect_color_support);
+    common::render_once(
+        &mut io::stdout(),
+        effect,
+        terminal::size()?,
+        color_mode,
+        args.invert,
+    )?;
+    println!();
     Ok(())
 }
 
+#[cfg(feature = "audio")]
+fn start_capture(level: &audio::AudioLevel) -> cpal::Stream {
+    audio::capture(level.clone()).unwrap_or_else(|e| {
+        eprintln!("Can't capture audio: {}", e);
+        process::exit(1);
+    })
+}
+
+#[cfg(not(feature = "audio"))]
+fn start_capture(_level: &audio::AudioLevel) {
+    eprintln!("Built without audio support, install with `--features audio`");
+    process::exit(1);
+}
+
 fn parse_args() -> Result<AppArgs, pico_args::Error> {
     let mut pargs = pico_args::Arguments::from_env();
 
     if pargs.contains(["-h", "--help"]) {
-        print!("{}", HELP);
+        // `tarts matrix --help` describes options of the effect
+        let saver = env::args()
+            .skip(1)
+            .find(|arg| common::VALID_SAVERS.contains(&arg.as_str()));
+        print!("{}", saver.map_or(HELP, |name| effect_help(&name)));
         process::exit(0);
     }
 
     let check = pargs.contains("--check");
     let effect = pargs.opt_value_from_str("--effect")?;
     let frames = pargs.opt_value_from_str("--frames")?;
+    let seed = pargs.opt_value_from_str("--seed")?;
+    let no_alt_screen = pargs.contains("--no-alt-screen");
+    let color_mode = pargs.opt_value_from_str("--color-mode")?;
+    let record = pargs.opt_value_from_str("--record")?;
+    let record_cast = pargs.opt_value_from_str("--record-cast")?;
+    let rule = pargs.opt_value_from_str("--rule")?;
+    let lock = pargs.opt_value_from_str("--lock")?;
+    let idle = pargs.opt_value_from_str("--idle")?;
+    let pattern = pargs.opt_value_from_str("--pattern")?;
+    let reactive = pargs.contains("--reactive");
+    let density = pargs.opt_value_from_str("--density")?;
+    let hue = pargs.opt_value_from_str("--hue")?;
+    let glitch = pargs.contains("--glitch");
+    let source = pargs.opt_value_from_str("--source")?;
+    let twelve_hour = pargs.contains("--12h");
+    let no_seconds = pargs.contains("--no-seconds");
+    let once = pargs.contains("--once");
+    let stats_json = pargs.contains("--stats-json");
+    let cycle = pargs.opt_value_from_fn("--cycle", |list: &str| {
+        Ok::<_, String>(
+            list.split(',')
+                .map(|name| name.trim().to_string())
+                .collect(),
+        )
+    })?;
+    let interval = pargs.opt_value_from_str("--interval")?;
+    let bg = pargs.opt_value_from_fn("--bg", color::parse_color)?;
+    let invert = pargs.contains("--invert");
+    let pan = pargs.contains("--pan");
+    let pause = pargs.opt_value_from_str("--pause")?;
+    let steps = pargs.opt_value_from_str("--steps")?;
+    let algorithm = pargs.opt_value_from_str("--algorithm")?;
+    let style = pargs.opt_value_from_str("--style")?;
 
     let args = AppArgs {
         screen_saver: pargs.free_from_str().map_or("matrix".into(), |arg| arg),
         check,
         effect,
         frames,
+        seed,
+        no_alt_screen,
+        color_mode,
+        record,
+        record_cast,
+        rule,
+        lock,
+        idle,
+        pattern,
+        reactive,
+        density,
+        hue,
+        glitch,
+        source,
+        twelve_hour,
+        no_seconds,
+        once,
+        stats_json,
+        cycle,
+        interval,
+        bg,
+        invert,
+        pan,
+        pause,
+        steps,
+        algorithm,
+        style,
     };
 
     let remaining = pargs.finish();
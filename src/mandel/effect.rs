@@ -0,0 +1,242 @@
+//! Slow zoom into Mandelbrot set, or Julia set when its constant is given.
+//! Each cell is a point of complex plane, number of iterations before the
+//! point escapes picks color from the palette, points of the set are black.
+use crate::buffer::{Buffer, Cell};
+use crate::common::TerminalEffect;
+use crate::rain::gradient;
+use crossterm::style;
+use derive_builder::Builder;
+
+/// Width of the complex plane shown on screen before zooming
+const START_SCALE: f64 = 3.5;
+
+/// Zoom starts over below this width, f64 runs out of precision
+const MIN_SCALE: f64 = 1e-12;
+
+/// Terminal cells are about twice higher than wide
+const CELL_ASPECT: f64 = 2.0;
+
+/// Number of colors escape time is cycled through
+const PALETTE_SIZE: usize = 48;
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct MandelOptions {
+    screen_size: (u16, u16),
+    /// Point of complex plane zoom is heading to
+    #[builder(default = "(-0.743643887037151, 0.131825904205330)")]
+    center: (f64, f64),
+    /// Scale is divided by this value every frame
+    #[builder(default = "1.01")]
+    zoom_speed: f64,
+    /// Points which don't escape in this number of iterations are in the set
+    #[builder(default = "256")]
+    max_iterations: u32,
+    /// Constant of Julia set, Mandelbrot set is drawn if not set
+    #[builder(default)]
+    julia: Option<(f64, f64)>,
+}
+
+pub struct Mandel {
+    options: MandelOptions,
+    buffer: Buffer,
+    /// Next frame is drawn here and swapped with `buffer`
+    back_buffer: Buffer,
+    palette: Vec<style::Color>,
+    /// Width of the complex plane visible on screen
+    scale: f64,
+}
+
+impl TerminalEffect for Mandel {
+    fn name(&self) -> &'static str {
+        "mandel"
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        self.back_buffer.clear();
+        self.fill_buffer();
+
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
+        diff
+    }
+
+    fn update(&mut self) {
+        self.scale /= self.options.zoom_speed;
+        if self.scale < MIN_SCALE {
+            self.scale = START_SCALE;
+        }
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.options.clone());
+    }
+
+    /// Zoom goes on from the same scale
+    fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        self.buffer = Buffer::new(width as usize, height as usize);
+        self.back_buffer = self.buffer.clone();
+    }
+}
+
+impl Mandel {
+    /// Printed by `tarts mandel --help`
+    pub fn help() -> &'static str {
+        "mandel: slow zoom into Mandelbrot set, starts over when f64 runs out
+of precision
+
+Has no options.
+"
+    }
+
+    pub fn new(options: MandelOptions) -> Self {
+        let buffer = Buffer::new(
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        Self {
+            options,
+            back_buffer: buffer.clone(),
+            buffer,
+            palette: palette(),
+            scale: START_SCALE,
+        }
+    }
+
+    /// Point of complex plane under the cell
+    fn to_complex(&self, x: usize, y: usize) -> (f64, f64) {
+        let (width, height) = (self.buffer.width as f64, self.buffer.height as f64);
+        let step = self.scale / width;
+        (
+            self.options.center.0 + (x as f64 - width / 2.0) * step,
+            self.options.center.1 + (y as f64 - height / 2.0) * step * CELL_ASPECT,
+        )
+    }
+
+    fn fill_buffer(&mut self) {
+        for y in 0..self.back_buffer.height {
+            for x in 0..self.back_buffer.width {
+                let point = self.to_complex(x, y);
+                let escaped = match self.options.julia {
+                    Some(c) => escape_time(point, c, self.options.max_iterations),
+                    None => {
+                        escape_time((0.0, 0.0), point, self.options.max_iterations)
+                    }
+                };
+                if let Some(iterations) = escaped {
+                    let color =
+                        self.palette[iterations as usize % self.palette.len()];
+                    self.back_buffer.set(
+                        x,
+                        y,
+                        Cell::new('█', color, style::Attribute::NormalIntensity),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Iterate z = z^2 + c, number of iterations before |z| > 2 or `None` if
+/// point did not escape
+pub fn escape_time(
+    z: (f64, f64),
+    c: (f64, f64),
+    max_iterations: u32,
+) -> Option<u32> {
+    let (mut re, mut im) = z;
+    for iteration in 0..max_iterations {
+        if re * re + im * im > 4.0 {
+            return Some(iteration);
+        }
+        (re, im) = (re * re - im * im + c.0, 2.0 * re * im + c.1);
+    }
+    None
+}
+
+/// Deep blue through orange to white and back, so cycled colors don't jump
+fn palette() -> Vec<style::Color> {
+    let forward = gradient::two_step_color_gradient(
+        gradient::Color { r: 0, g: 7, b: 100 },
+        gradient::Color {
+            r: 255,
+            g: 170,
+            b: 0,
+        },
+        gradient::Color {
+            r: 237,
+            g: 255,
+            b: 255,
+        },
+        PALETTE_SIZE / 4,
+        PALETTE_SIZE / 2,
+        false,
+    );
+    forward
+        .iter()
+        .chain(forward.iter().rev())
+        .map(|c| style::Color::Rgb {
+            r: c.r,
+            g: c.g,
+            b: c.b,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_mandel(center: (f64, f64)) -> Mandel {
+        let options = MandelOptionsBuilder::default()
+            .screen_size((40, 20))
+            .center(center)
+            .build()
+            .unwrap();
+        Mandel::new(options)
+    }
+
+    #[test]
+    fn points_of_set_do_not_escape() {
+        assert_eq!(escape_time((0.0, 0.0), (0.0, 0.0), 100), None);
+        assert_eq!(escape_time((0.0, 0.0), (-1.0, 0.0), 100), None);
+        assert_eq!(escape_time((0.0, 0.0), (2.0, 2.0), 100), Some(1));
+        assert!(escape_time((0.0, 0.0), (0.5, 0.5), 100).is_some());
+    }
+
+    #[test]
+    fn center_of_set_is_blank() {
+        let mut mandel = get_mandel((-0.5, 0.0));
+        let diff = mandel.get_diff();
+        assert!(!diff.is_empty());
+        assert_eq!(mandel.buffer.get(20, 10), Cell::default());
+        // corners are far outside of the set
+        assert_eq!(mandel.buffer.get(0, 0).symbol, '█');
+    }
+
+    #[test]
+    fn zoom_starts_over() {
+        let mut mandel = get_mandel((-0.5, 0.0));
+        mandel.update();
+        assert!(mandel.scale < START_SCALE);
+
+        mandel.scale = MIN_SCALE * 1.001;
+        mandel.update();
+        assert_eq!(mandel.scale, START_SCALE);
+    }
+
+    #[test]
+    fn julia_differs_from_mandelbrot() {
+        let mut mandel = get_mandel((0.0, 0.0));
+        let mut julia = Mandel::new(MandelOptions {
+            julia: Some((-0.8, 0.156)),
+            ..mandel.options.clone()
+        });
+        assert_ne!(mandel.get_diff(), julia.get_diff());
+    }
+}
@@ -0,0 +1,315 @@
+//! Maze generation algorithms. Cells of the maze are on even coordinates,
+//! cells between them are walls which get carved to connect two cells.
+//! Each algorithm carves the maze incrementally, step by step, so it can
+//! be watched while growing.
+use crate::common::EffectRng;
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    collections::{HashSet, VecDeque},
+    str::FromStr,
+};
+
+type Position = (usize, usize);
+
+/// Maze generation algorithm
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    /// Randomized depth-first search, long winding corridors
+    #[default]
+    Backtracker,
+    /// Randomized Prim's, grows from one cell, lots of short dead ends
+    Prim,
+    /// Randomized Kruskal's, joins random pieces all over the screen
+    Kruskal,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "backtracker" => Ok(Algorithm::Backtracker),
+            "prim" => Ok(Algorithm::Prim),
+            "kruskal" => Ok(Algorithm::Kruskal),
+            _ => Err(format!(
+                "unknown maze algorithm {}, use backtracker, prim or kruskal",
+                s
+            )),
+        }
+    }
+}
+
+/// State of the algorithm driving maze carving
+pub trait Generator {
+    /// Make one step, returns false once there is nothing left to carve
+    fn step(
+        &mut self,
+        paths: &mut HashSet<(usize, usize)>,
+        rng: &mut EffectRng,
+    ) -> bool;
+}
+
+/// Create generator for maze of given size
+pub fn new_generator(
+    algorithm: Algorithm,
+    size: (usize, usize),
+    rng: &mut EffectRng,
+) -> Box<dyn Generator> {
+    match algorithm {
+        Algorithm::Backtracker => Box::new(Backtracker::new(size, rng)),
+        Algorithm::Prim => Box::new(Prim::new(size, rng)),
+        Algorithm::Kruskal => Box::new(Kruskal::new(size, rng)),
+    }
+}
+
+/// Pick start on even coordinates, carving goes with step 2 so the first
+/// row and column are reachable only from even position
+fn random_start(
+    (width, height): (usize, usize),
+    rng: &mut impl Rng,
+) -> (usize, usize) {
+    let x = rng.gen_range(0..=(width as isize - 1) / 2) * 2;
+    let y = rng.gen_range(0..=(height as isize - 1) / 2) * 2;
+    (x as usize, y as usize)
+}
+
+/// Cells two steps away in each direction inside of the maze, with the
+/// wall between
+fn neighbor_cells(
+    (x, y): (usize, usize),
+    (width, height): (usize, usize),
+) -> Vec<(Position, Position)> {
+    let mut neighbors = vec![];
+    if x >= 2 {
+        neighbors.push(((x - 2, y), (x - 1, y)));
+    }
+    if x + 2 < width {
+        neighbors.push(((x + 2, y), (x + 1, y)));
+    }
+    if y >= 2 {
+        neighbors.push(((x, y - 2), (x, y - 1)));
+    }
+    if y + 2 < height {
+        neighbors.push(((x, y + 2), (x, y + 1)));
+    }
+    neighbors
+}
+
+pub struct Backtracker {
+    size: (usize, usize),
+    stack: VecDeque<(isize, isize)>,
+    start: Option<(usize, usize)>,
+}
+
+impl Backtracker {
+    fn new(size: (usize, usize), rng: &mut EffectRng) -> Self {
+        let (x, y) = random_start(size, rng);
+        Self {
+            size,
+            stack: VecDeque::from([(x as isize, y as isize)]),
+            start: Some((x, y)),
+        }
+    }
+
+    fn is_valid_cell(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.size.0 && (y as usize) < self.size.1
+    }
+}
+
+impl Generator for Backtracker {
+    fn step(
+        &mut self,
+        paths: &mut HashSet<(usize, usize)>,
+        rng: &mut EffectRng,
+    ) -> bool {
+        if let Some(start) = self.start.take() {
+            paths.insert(start);
+            return true;
+        }
+
+        let Some((x, y)) = self.stack.pop_back() else {
+            return false;
+        };
+        let directions = [(2, 0), (0, 2), (-2, 0), (0, -2)]; // Skip one cell to maintain walls
+        let mut shuffled_directions = directions;
+        shuffled_directions.shuffle(rng);
+
+        for &(dx, dy) in &shuffled_directions {
+            let new_x = x + dx;
+            let new_y = y + dy;
+
+            // Check the cell to be carved and the wall between the current and new cell
+            if self.is_valid_cell(new_x, new_y)
+                && self.is_valid_cell(x + dx / 2, y + dy / 2)
+                && !paths.contains(&(new_x as usize, new_y as usize))
+            {
+                // Carve path for both the new cell and the wall between
+                paths.insert((new_x as usize, new_y as usize));
+                paths.insert(((x + dx / 2) as usize, (y + dy / 2) as usize));
+                // Push the current position back for backtracking
+                self.stack.push_back((x, y));
+                self.stack.push_back((new_x, new_y)); // Push the new position
+                break;
+            }
+        }
+        // at a dead-end the cell is already popped, so next step backtracks
+        true
+    }
+}
+
+pub struct Prim {
+    size: (usize, usize),
+    /// Uncarved cells next to the maze, with the wall leading to them
+    frontier: Vec<(Position, Position)>,
+    start: Option<(usize, usize)>,
+}
+
+impl Prim {
+    fn new(size: (usize, usize), rng: &mut EffectRng) -> Self {
+        Self {
+            size,
+            frontier: vec![],
+            start: Some(random_start(size, rng)),
+        }
+    }
+
+    fn add_frontier(
+        &mut self,
+        cell: (usize, usize),
+        paths: &HashSet<(usize, usize)>,
+    ) {
+        for (neighbor, wall) in neighbor_cells(cell, self.size) {
+            if !paths.contains(&neighbor) {
+                self.frontier.push((neighbor, wall));
+            }
+        }
+    }
+}
+
+impl Generator for Prim {
+    fn step(
+        &mut self,
+        paths: &mut HashSet<(usize, usize)>,
+        rng: &mut EffectRng,
+    ) -> bool {
+        if let Some(start) = self.start.take() {
+            paths.insert(start);
+            self.add_frontier(start, paths);
+            return true;
+        }
+
+        // frontier can have cells carved since they were added
+        while !self.frontier.is_empty() {
+            let index = rng.gen_range(0..self.frontier.len());
+            let (cell, wall) = self.frontier.swap_remove(index);
+            if paths.contains(&cell) {
+                continue;
+            }
+            paths.insert(wall);
+            paths.insert(cell);
+            self.add_frontier(cell, paths);
+            return true;
+        }
+        false
+    }
+}
+
+pub struct Kruskal {
+    width: usize,
+    /// Walls between cells in random order
+    walls: Vec<(Position, Position, Position)>,
+    /// Disjoint sets of connected cells, parent index of each cell
+    parents: Vec<usize>,
+}
+
+impl Kruskal {
+    fn new(size: (usize, usize), rng: &mut EffectRng) -> Self {
+        let (width, height) = size;
+        let mut walls = vec![];
+        for y in (0..height).step_by(2) {
+            for x in (0..width).step_by(2) {
+                for (neighbor, wall) in neighbor_cells((x, y), size) {
+                    // each wall once, to the right or down
+                    if neighbor > (x, y) {
+                        walls.push(((x, y), neighbor, wall));
+                    }
+                }
+            }
+        }
+        walls.shuffle(rng);
+
+        Self {
+            width,
+            walls,
+            parents: (0..width * height).collect(),
+        }
+    }
+
+    fn find(&mut self, cell: (usize, usize)) -> usize {
+        let mut index = cell.1 * self.width + cell.0;
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+}
+
+impl Generator for Kruskal {
+    fn step(
+        &mut self,
+        paths: &mut HashSet<(usize, usize)>,
+        _rng: &mut EffectRng,
+    ) -> bool {
+        // walls between already connected cells are kept
+        while let Some((first, second, wall)) = self.walls.pop() {
+            let (first_set, second_set) = (self.find(first), self.find(second));
+            if first_set == second_set {
+                continue;
+            }
+            self.parents[first_set] = second_set;
+            paths.insert(first);
+            paths.insert(wall);
+            paths.insert(second);
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::make_rng;
+
+    #[test]
+    fn algorithm_from_str() {
+        assert_eq!("prim".parse(), Ok(Algorithm::Prim));
+        assert_eq!("kruskal".parse(), Ok(Algorithm::Kruskal));
+        assert!("dfs".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn every_cell_connected_once() {
+        for algorithm in
+            [Algorithm::Backtracker, Algorithm::Prim, Algorithm::Kruskal]
+        {
+            for size in [(9, 7), (21, 11), (10, 8)] {
+                for seed in 0..50 {
+                    let mut rng = make_rng(Some(seed));
+                    let mut generator = new_generator(algorithm, size, &mut rng);
+                    let mut paths = HashSet::new();
+                    while generator.step(&mut paths, &mut rng) {}
+
+                    // all cells joined into a tree by one wall less
+                    let total = size.0.div_ceil(2) * size.1.div_ceil(2);
+                    let cells =
+                        paths.iter().filter(|(x, y)| x % 2 == 0 && y % 2 == 0);
+                    let context = (algorithm, size, seed);
+                    assert_eq!(cells.count(), total, "{:?}", context);
+                    assert_eq!(paths.len(), 2 * total - 1, "{:?}", context);
+                }
+            }
+        }
+    }
+}
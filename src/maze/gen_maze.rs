@@ -1,10 +1,14 @@
 use crate::buffer::{Buffer, Cell};
-use crate::common::TerminalEffect;
+use crate::common::{make_rng, EffectRng, TerminalEffect};
+use crate::maze::algorithms::{new_generator, Algorithm, Generator};
 use crossterm::style;
 use derive_builder::Builder;
 use once_cell::sync::Lazy;
-use rand::{seq::SliceRandom, Rng};
-use std::collections::{HashMap, HashSet, VecDeque};
+use rand::Rng;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+};
 
 /// Characters in form of hashmap with label as key
 static CHARACTERS_MAP: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
@@ -24,10 +28,84 @@ static CHARACTERS: Lazy<Vec<char>> = Lazy::new(|| {
     v
 });
 
+/// Frames completed maze stays on screen by default, two seconds at 60 fps
+pub const COMPLETE_PAUSE: u32 = 120;
+
+/// Color of the revealed solution path
+const SOLUTION_COLOR: style::Color = style::Color::Rgb {
+    r: 255,
+    g: 80,
+    b: 80,
+};
+
+/// Color of box drawing walls
+const WALL_COLOR: style::Color = style::Color::Rgb {
+    r: 90,
+    g: 200,
+    b: 120,
+};
+
+/// Box drawing character for wall connected to neighbor walls, indexed by
+/// mask of connections: up 1, right 2, down 4, left 8
+const BOX_WALLS: [char; 16] = [
+    '·', '│', '─', '└', '│', '│', '┌', '├', '─', '┘', '─', '┴', '┐', '┤', '┬', '┼',
+];
+
+/// How maze is drawn
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum RenderStyle {
+    /// Walls of random blinking characters, paths are white blocks
+    #[default]
+    Glitch,
+    /// Walls of box drawing lines, paths are empty
+    BoxDrawing,
+}
+
+impl FromStr for RenderStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "glitch" => Ok(RenderStyle::Glitch),
+            "box" => Ok(RenderStyle::BoxDrawing),
+            _ => Err(format!("unknown maze style {}, use glitch or box", s)),
+        }
+    }
+}
+
 #[derive(Builder, Default, Debug, Clone)]
 #[builder(public, setter(into))]
 pub struct MazeOptions {
     screen_size: (u16, u16),
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    seed: Option<u64>,
+    /// Number of frames to show completed maze before starting new one
+    #[builder(default = "COMPLETE_PAUSE")]
+    complete_pause: u32,
+    /// Walk the path from top-left to bottom-right corner once maze is done
+    #[builder(default)]
+    show_solution: bool,
+    /// Number of carving steps made each frame
+    #[builder(default = "1")]
+    steps_per_frame: u32,
+    #[builder(default)]
+    algorithm: Algorithm,
+    /// Glitchy characters or box drawing lines for walls
+    #[builder(default)]
+    render_style: RenderStyle,
+}
+
+/// Stage of the maze lifecycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MazePhase {
+    Generating,
+    /// Solution path is revealed one cell per update
+    Solving,
+    /// Maze is done and stays on the screen for a few more frames
+    Done {
+        frames_left: u32,
+    },
 }
 
 pub struct Maze {
@@ -35,45 +113,45 @@ pub struct Maze {
     buffer: Buffer,
     initial_walls: Buffer,
     paths: HashSet<(usize, usize)>,
-    stack: VecDeque<(isize, isize)>,
-    maze_complete: bool,
-    pub rng: rand::prelude::ThreadRng,
+    generator: Box<dyn Generator>,
+    solution: Vec<(usize, usize)>,
+    solution_shown: usize,
+    phase: MazePhase,
+    pub rng: EffectRng,
 }
 
 impl TerminalEffect for Maze {
+    fn name(&self) -> &'static str {
+        "maze"
+    }
+
+    /// Room for two paths with wall between them in both directions
+    fn min_size(&self) -> (u16, u16) {
+        (3, 3)
+    }
+
     fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
-        if self.maze_complete {
-            self.reset();
+        if let MazePhase::Done { frames_left } = self.phase {
+            match frames_left {
+                0 => self.reset(),
+                _ => {
+                    self.phase = MazePhase::Done {
+                        frames_left: frames_left - 1,
+                    }
+                }
+            };
             return Vec::new();
         }
-        let mut curr_buffer = self.initial_walls.clone();
-        let mut modified_cells = HashSet::new();
-        // Randomly change 5 distinct cells
-        while modified_cells.len() < 3 {
-            let x = self.rng.gen_range(0..curr_buffer.width);
-            let y = self.rng.gen_range(0..curr_buffer.height);
-
-            if modified_cells.insert((x, y)) {
-                let random_char =
-                    CHARACTERS[self.rng.gen_range(0..CHARACTERS.len())];
-                let random_color = style::Color::Rgb {
-                    r: self.rng.gen_range(0..200) as u8,
-                    g: self.rng.gen_range(0..256) as u8,
-                    b: self.rng.gen_range(0..200) as u8,
-                };
-                self.initial_walls.set(
-                    x,
-                    y,
-                    Cell::new(random_char, random_color, style::Attribute::Bold),
-                );
-            }
-        }
+        let mut curr_buffer = match self.options.render_style {
+            RenderStyle::Glitch => self.glitch_walls(),
+            RenderStyle::BoxDrawing => self.box_walls(),
+        };
 
-        for (x, y) in self.paths.iter() {
+        for (x, y) in self.solution.iter().take(self.solution_shown) {
             curr_buffer.set(
                 *x,
                 *y,
-                Cell::new('█', style::Color::White, style::Attribute::Reset),
+                Cell::new('█', SOLUTION_COLOR, style::Attribute::Reset),
             )
         }
 
@@ -83,43 +161,22 @@ impl TerminalEffect for Maze {
     }
 
     fn update(&mut self) {
-        if self.maze_complete {
-            return;
-        }
-
-        if let Some((x, y)) = self.stack.pop_back() {
-            let directions = [(2, 0), (0, 2), (-2, 0), (0, -2)]; // Skip one cell to maintain walls
-            let mut shuffled_directions = directions;
-            shuffled_directions.shuffle(&mut self.rng);
-
-            let mut moved = false;
-            for &(dx, dy) in &shuffled_directions {
-                let new_x = x + dx;
-                let new_y = y + dy;
-
-                // Check the cell to be carved and the wall between the current and new cell
-                if self.is_valid_cell(new_x, new_y)
-                    && self.is_valid_cell(x + dx / 2, y + dy / 2)
-                    && !self.paths.contains(&(new_x as usize, new_y as usize))
-                {
-                    // Carve path for both the new cell and the wall between
-                    self.carve_path(new_x, new_y);
-                    self.carve_path(x + dx / 2, y + dy / 2);
-                    // Push the current position back for backtracking
-                    self.stack.push_back((x, y));
-                    self.stack.push_back((new_x, new_y)); // Push the new position
-                    moved = true;
-                    break;
+        if self.is_complete() {
+            if self.phase == MazePhase::Solving {
+                if self.solution_shown < self.solution.len() {
+                    self.solution_shown += 1;
+                } else {
+                    self.finish();
                 }
             }
+            return;
+        }
 
-            if !moved {
-                // If we didn't move, it means we're at a dead-end and need to backtrack
-                self.stack.pop_back();
+        for _ in 0..self.options.steps_per_frame.max(1) {
+            self.carve_step();
+            if self.is_complete() {
+                break;
             }
-        } else {
-            // If the stack is empty, the maze is complete
-            self.maze_complete = true;
         }
     }
 
@@ -128,66 +185,202 @@ impl TerminalEffect for Maze {
     }
 
     fn reset(&mut self) {
-        let mut new_effect = Self::new(self.options.clone());
-        fill_initial_walls(&mut new_effect.initial_walls);
-        new_effect.maze_complete = false;
-        new_effect.paths.clear();
-        new_effect.stack.clear();
-        new_effect.rng = rand::thread_rng();
-
-        let start_x = new_effect
-            .rng
-            .gen_range(0..self.options.screen_size.0 as isize);
-        let start_y = new_effect
-            .rng
-            .gen_range(0..self.options.screen_size.1 as isize);
-        new_effect.stack.push_back((start_x, start_y));
+        // keep going with the same generator, so next maze is different
+        let new_effect = Self::with_rng(self.options.clone(), self.rng.clone());
         *self = new_effect;
     }
 }
 
 impl Maze {
+    /// Printed by `tarts maze --help`
+    pub fn help() -> &'static str {
+        "maze: maze carved step by step, solution is drawn when it's done and
+new maze is started
+
+Options:
+  --algorithm <a>   backtracker (long corridors, default), prim (short dead
+                    ends) or kruskal (joins pieces all over the screen)
+  --style <s>       glitch (blinking characters, default) or box (walls
+                    of box drawing lines)
+  --pause <frames>  how long finished maze stays on screen, 120 by default
+  --steps <n>       carving steps per frame, 1 by default, more finish
+                    sooner on large terminals
+  --seed <n>        seed random generator
+"
+    }
+
     pub fn new(options: MazeOptions) -> Self {
-        let mut rng = rand::thread_rng();
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Create maze with provided random generator
+    pub fn with_rng(options: MazeOptions, mut rng: EffectRng) -> Self {
         let buffer = Buffer::new(
             options.screen_size.0 as usize,
             options.screen_size.1 as usize,
         );
 
         let paths = HashSet::new();
-        let start_x = rng.gen_range(0..options.screen_size.0 as isize);
-        let start_y = rng.gen_range(0..options.screen_size.1 as isize);
-        let mut stack = VecDeque::new();
-        stack.push_back((start_x, start_y));
+        let generator = new_generator(
+            options.algorithm,
+            (
+                options.screen_size.0 as usize,
+                options.screen_size.1 as usize,
+            ),
+            &mut rng,
+        );
 
         let mut initial_walls = buffer.clone();
-        fill_initial_walls(&mut initial_walls);
+        fill_initial_walls(&mut initial_walls, &mut rng);
 
         Self {
             options,
             buffer,
             initial_walls,
             paths,
-            stack,
-            maze_complete: false,
+            generator,
+            solution: Vec::new(),
+            solution_shown: 0,
+            phase: MazePhase::Generating,
             rng,
         }
     }
 
-    fn is_valid_cell(&self, x: isize, y: isize) -> bool {
-        x >= 0
-            && y >= 0
-            && (x as usize) < (self.options.screen_size.0 as usize)
-            && (y as usize) < (self.options.screen_size.1 as usize)
+    /// Generation is finished
+    pub fn is_complete(&self) -> bool {
+        self.phase != MazePhase::Generating
+    }
+
+    fn finish(&mut self) {
+        self.phase = MazePhase::Done {
+            frames_left: self.options.complete_pause,
+        };
+    }
+
+    /// Shortest path between path cells closest to top-left and bottom-right
+    /// corners, found with BFS
+    fn solve(&self) -> Vec<(usize, usize)> {
+        let start = self.paths.iter().min_by_key(|&&(x, y)| (x + y, x));
+        let goal = self.paths.iter().max_by_key(|&&(x, y)| (x + y, x));
+        let (Some(&start), Some(&goal)) = (start, goal) else {
+            return Vec::new();
+        };
+
+        let mut came_from = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        came_from.insert(start, start);
+        while let Some((x, y)) = queue.pop_front() {
+            if (x, y) == goal {
+                break;
+            }
+            let neighbors = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for next in neighbors {
+                if self.paths.contains(&next) && !came_from.contains_key(&next) {
+                    came_from.insert(next, (x, y));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        if !came_from.contains_key(&goal) {
+            return Vec::new();
+        }
+        let mut path = vec![goal];
+        let mut current = goal;
+        while current != start {
+            current = came_from[&current];
+            path.push(current);
+        }
+        path.reverse();
+        path
+    }
+
+    /// One step of the generation algorithm, maze is complete when there
+    /// is nothing left to carve
+    fn carve_step(&mut self) {
+        if self.generator.step(&mut self.paths, &mut self.rng) {
+            return;
+        }
+        if self.options.show_solution {
+            self.solution = self.solve();
+            self.phase = MazePhase::Solving;
+        } else {
+            self.finish();
+        }
+    }
+
+    /// Walls of random characters with a few of them changed each frame,
+    /// paths drawn over them
+    fn glitch_walls(&mut self) -> Buffer {
+        let mut curr_buffer = self.initial_walls.clone();
+        let mut modified_cells = HashSet::new();
+        // Randomly change 5 distinct cells
+        while modified_cells.len() < 3 {
+            let x = self.rng.gen_range(0..curr_buffer.width);
+            let y = self.rng.gen_range(0..curr_buffer.height);
+
+            if modified_cells.insert((x, y)) {
+                let random_char =
+                    CHARACTERS[self.rng.gen_range(0..CHARACTERS.len())];
+                let random_color = style::Color::Rgb {
+                    r: self.rng.gen_range(0..200) as u8,
+                    g: self.rng.gen_range(0..256) as u8,
+                    b: self.rng.gen_range(0..200) as u8,
+                };
+                self.initial_walls.set(
+                    x,
+                    y,
+                    Cell::new(random_char, random_color, style::Attribute::Bold),
+                );
+            }
+        }
+
+        for (x, y) in self.paths.iter() {
+            curr_buffer.set(
+                *x,
+                *y,
+                Cell::new('█', style::Color::White, style::Attribute::Reset),
+            )
+        }
+        curr_buffer
     }
 
-    fn carve_path(&mut self, x: isize, y: isize) {
-        self.paths.insert((x as usize, y as usize));
+    /// Every cell which is not a path is a wall, drawn with box line
+    /// joining it to the neighbor walls
+    fn box_walls(&self) -> Buffer {
+        let (width, height) = (self.buffer.width, self.buffer.height);
+        let mut curr_buffer = Buffer::new(width, height);
+        let is_wall = |x: usize, y: usize| {
+            x < width && y < height && !self.paths.contains(&(x, y))
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                if !is_wall(x, y) {
+                    continue;
+                }
+                let mask = (y > 0 && is_wall(x, y - 1)) as usize
+                    | (is_wall(x + 1, y) as usize) << 1
+                    | (is_wall(x, y + 1) as usize) << 2
+                    | ((x > 0 && is_wall(x - 1, y)) as usize) << 3;
+                curr_buffer.set(
+                    x,
+                    y,
+                    Cell::new(BOX_WALLS[mask], WALL_COLOR, style::Attribute::Reset),
+                );
+            }
+        }
+        curr_buffer
     }
 }
 
-fn fill_initial_walls(buffer: &mut Buffer) {
-    let mut rng = rand::thread_rng();
+fn fill_initial_walls(buffer: &mut Buffer, rng: &mut impl Rng) {
     for y in 0..buffer.height {
         for x in 0..buffer.width {
             let random_char = CHARACTERS[rng.gen_range(0..CHARACTERS.len())];
@@ -227,10 +420,9 @@ mod tests {
         assert_eq!(initialized_cells, 0);
         assert_eq!(maze.initial_walls.buffer.len(), 9);
 
-        // path and stack are empty, and maze is not completed
+        // nothing carved yet, and maze is not completed
         assert!(maze.paths.is_empty());
-        assert!(maze.stack.len() == 1);
-        assert!(!maze.maze_complete);
+        assert!(!maze.is_complete());
     }
 
     #[test]
@@ -240,6 +432,8 @@ mod tests {
             .build()
             .unwrap();
         let mut maze = Maze::new(options);
+        // start cell is carved first, then the wall and cell next to it
+        maze.update();
         maze.update();
         let diff = maze.get_diff();
         assert_eq!(diff.len(), 25);
@@ -254,7 +448,171 @@ mod tests {
                 path_cells += 1;
             }
         }
-        assert_eq!(path_cells, 23);
+        assert_eq!(path_cells, 22);
         // */
     }
+
+    #[test]
+    fn same_rng_same_maze() {
+        let options = MazeOptionsBuilder::default()
+            .screen_size((9, 7))
+            .build()
+            .unwrap();
+        let mut first = Maze::with_rng(options.clone(), make_rng(Some(7)));
+        let mut second = Maze::with_rng(options, make_rng(Some(7)));
+        for _ in 0..20 {
+            first.update();
+            second.update();
+            assert_eq!(first.get_diff(), second.get_diff());
+        }
+        assert_eq!(first.paths, second.paths);
+    }
+
+    #[test]
+    fn carve_reaches_edges() {
+        let options = MazeOptionsBuilder::default()
+            .screen_size((7, 5))
+            .build()
+            .unwrap();
+        let mut maze = Maze::new(options);
+        while !maze.is_complete() {
+            maze.update();
+        }
+        assert!(maze.paths.iter().any(|&(x, _)| x == 0));
+        assert!(maze.paths.iter().any(|&(_, y)| y == 0));
+    }
+
+    #[test]
+    fn pause_after_completion() {
+        let options = MazeOptionsBuilder::default()
+            .screen_size((5, 5))
+            .complete_pause(3_u32)
+            .build()
+            .unwrap();
+        let mut maze = Maze::new(options);
+        while !maze.is_complete() {
+            let _ = maze.get_diff();
+            maze.update();
+        }
+        let final_paths = maze.paths.clone();
+        let final_buffer = maze.buffer.buffer.clone();
+
+        for _ in 0..3 {
+            assert!(maze.get_diff().is_empty());
+            maze.update();
+            assert!(maze.is_complete());
+            assert_eq!(maze.paths, final_paths);
+            assert_eq!(maze.buffer.buffer, final_buffer);
+        }
+
+        // pause is over, new maze started
+        let _ = maze.get_diff();
+        assert_eq!(maze.phase, MazePhase::Generating);
+        assert!(maze.paths.is_empty());
+    }
+
+    #[test]
+    fn more_steps_per_frame_finish_sooner() {
+        let updates_to_complete = |steps: u32| {
+            let options = MazeOptionsBuilder::default()
+                .screen_size((21, 11))
+                .steps_per_frame(steps)
+                .build()
+                .unwrap();
+            let mut maze = Maze::with_rng(options, make_rng(Some(5)));
+            let mut updates: u32 = 0;
+            while !maze.is_complete() {
+                maze.update();
+                updates += 1;
+            }
+            (updates, maze.paths)
+        };
+        let (slow, slow_paths) = updates_to_complete(1);
+        let (fast, fast_paths) = updates_to_complete(10);
+        assert_eq!(fast, slow.div_ceil(10));
+        // same generator carves the same maze
+        assert_eq!(slow_paths, fast_paths);
+    }
+
+    #[test]
+    fn every_algorithm_completes() {
+        for algorithm in
+            [Algorithm::Backtracker, Algorithm::Prim, Algorithm::Kruskal]
+        {
+            let options = MazeOptionsBuilder::default()
+                .screen_size((15, 9))
+                .algorithm(algorithm)
+                .show_solution(true)
+                .build()
+                .unwrap();
+            let mut maze = Maze::with_rng(options, make_rng(Some(2)));
+            for _ in 0..1000 {
+                if maze.is_complete() {
+                    break;
+                }
+                maze.update();
+            }
+            assert!(maze.is_complete(), "{:?}", algorithm);
+            // corners are connected
+            assert_eq!(maze.solution.first(), Some(&(0, 0)), "{:?}", algorithm);
+            assert_eq!(maze.solution.last(), Some(&(14, 8)), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn box_drawing_walls_joined() {
+        let options = MazeOptionsBuilder::default()
+            .screen_size((5, 3))
+            .render_style(RenderStyle::BoxDrawing)
+            .build()
+            .unwrap();
+        let mut maze = Maze::new(options);
+        // corridor along the middle row
+        maze.paths = (0..4).map(|x| (x, 1)).collect();
+        let _ = maze.get_diff();
+        assert_eq!(
+            crate::common::buffer_to_string(&maze.buffer),
+            "────┐\n    │\n────┘"
+        );
+    }
+
+    #[test]
+    fn solution_revealed_after_generation() {
+        let options = MazeOptionsBuilder::default()
+            .screen_size((9, 7))
+            .show_solution(true)
+            .build()
+            .unwrap();
+        let mut maze = Maze::with_rng(options, make_rng(Some(3)));
+        while !maze.is_complete() {
+            maze.update();
+        }
+        assert_eq!(maze.phase, MazePhase::Solving);
+
+        // path goes from corner to corner through carved cells
+        let solution = maze.solution.clone();
+        assert_eq!(solution.first(), Some(&(0, 0)));
+        assert_eq!(solution.last(), Some(&(8, 6)));
+        for pair in solution.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1);
+            assert!(maze.paths.contains(&b));
+        }
+
+        for shown in 1..=solution.len() {
+            maze.update();
+            assert_eq!(maze.solution_shown, shown);
+        }
+        let _ = maze.get_diff();
+        let (x, y) = solution[solution.len() - 1];
+        assert_eq!(maze.buffer.get(x, y).color, SOLUTION_COLOR);
+
+        maze.update();
+        assert_eq!(
+            maze.phase,
+            MazePhase::Done {
+                frames_left: COMPLETE_PAUSE
+            }
+        );
+    }
 }
@@ -1,2 +1,6 @@
+pub mod algorithms;
 pub mod gen_maze;
-pub use gen_maze::{Maze, MazeOptionsBuilder};
+pub use algorithms::Algorithm;
+pub use gen_maze::{
+    Maze, MazeOptions, MazeOptionsBuilder, MazePhase, RenderStyle, COMPLETE_PAUSE,
+};
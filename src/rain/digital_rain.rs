@@ -1,18 +1,238 @@
 use super::draw::{pick_color, pick_style};
 use super::gradient;
-use super::rain_drop::RainDrop;
+use super::rain_drop::{char_category, RainDrop};
 use crate::buffer::{Buffer, Cell};
-use crate::common::TerminalEffect;
+use crate::color;
+use crate::common::{make_rng, EffectRng, TerminalEffect};
 
+use crossterm::style;
 use derive_builder::Builder;
-use rand::{self, Rng};
-use std::time::Duration;
+use rand::Rng;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-#[derive(Builder, Default, Debug, PartialEq, Clone)]
+/// Color of revealed message characters
+const MESSAGE_COLOR: style::Color = style::Color::Rgb {
+    r: 190,
+    g: 255,
+    b: 190,
+};
+
+/// Shortest lane drops look like drops in
+const MIN_FALL_LENGTH: u16 = 8;
+
+/// Ghost trails darker than this are cleared
+const MIN_GHOST_LEVEL: u8 = 12;
+
+/// Drops faster than this, cells per second, are hard to read
+pub const MAX_SPEED: u16 = 30;
+
+/// Longer drops cover tall screens almost at once
+pub const MAX_DROP_LENGTH: u16 = 40;
+
+/// Scrambled cells of glitches
+const GLITCH_CHARACTERS: &[char] = &[
+    '#', '$', '%', '&', '@', '!', '?', '/', '\\', '|', '_', '░', '▒', '▓',
+];
+
+/// Biggest glitch rectangle, columns and rows
+const MAX_GLITCH_SIZE: (usize, usize) = (16, 4);
+
+/// Chance of glitch per frame used by `--glitch`
+pub const GLITCH_RATE: f64 = 0.05;
+
+/// Part of spawned drops sent to lanes of the message
+const MESSAGE_DROPS_SHARE: f64 = 0.3;
+
+/// Preset of drops number, multiplier of default drops range
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Density {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl Density {
+    pub fn drops_range(self, range: (u16, u16)) -> (u16, u16) {
+        let scale = |n: u16| match self {
+            Density::Low => n / 2,
+            Density::Medium => n,
+            Density::High => n.saturating_mul(2),
+        };
+        (scale(range.0), scale(range.1))
+    }
+}
+
+impl FromStr for Density {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Density::Low),
+            "medium" => Ok(Density::Medium),
+            "high" => Ok(Density::High),
+            _ => Err(format!("unknown density {}, use low, medium or high", s)),
+        }
+    }
+}
+
+/// How drops are colored across the screen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HueMode {
+    /// Classic green rain
+    #[default]
+    Green,
+    /// Horizontal rainbow, hue changes across columns
+    Rainbow,
+}
+
+impl FromStr for HueMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "green" => Ok(HueMode::Green),
+            "rainbow" => Ok(HueMode::Rainbow),
+            _ => Err(format!("unknown hue mode {}, use green or rainbow", s)),
+        }
+    }
+}
+
+/// Text drops are spelled from instead of random characters, shared by
+/// all drops and cycled when it ends
+#[derive(Debug, Clone)]
+pub struct SourceText {
+    chars: Arc<[char]>,
+    position: Arc<AtomicUsize>,
+}
+
+impl SourceText {
+    /// Whitespace and control characters are skipped, `None` if nothing
+    /// is left
+    pub fn new(text: &str) -> Option<Self> {
+        let chars: Vec<char> = text
+            .chars()
+            .filter(|c| !c.is_whitespace() && !c.is_control())
+            .collect();
+        match chars.is_empty() {
+            true => None,
+            false => Some(Self {
+                chars: chars.into(),
+                position: Arc::new(AtomicUsize::new(0)),
+            }),
+        }
+    }
+
+    pub fn next_char(&self) -> char {
+        let position = self.position.fetch_add(1, Ordering::Relaxed);
+        self.chars[position % self.chars.len()]
+    }
+}
+
+/// Same text, wherever it is read from
+impl PartialEq for SourceText {
+    fn eq(&self, other: &Self) -> bool {
+        self.chars == other.chars
+    }
+}
+
+/// Stored in config as the text itself, read position is not kept
+impl Serialize for SourceText {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.chars.iter().collect::<String>())
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceText {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        SourceText::new(&text)
+            .ok_or_else(|| de::Error::custom("source text has no characters"))
+    }
+}
+
+/// Where drops are falling to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RainDirection {
+    #[default]
+    Down,
+    Up,
+    Left,
+    Right,
+}
+
+/// Options of the rain, can be stored in config, missing fields are taken
+/// from defaults
+#[derive(Builder, Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DigitalRainOptions {
     pub screen_size: (u16, u16),
     pub drops_range: (u16, u16),
     pub speed_range: (u16, u16),
+    /// Color of the first character of the drop
+    #[builder(default = "style::Color::White")]
+    pub head_color: style::Color,
+    /// Number of bold glowing cells right after the head
+    #[builder(default = "5")]
+    pub glow_length: u16,
+    /// Number of cells after the glowing ones through which the drop
+    /// fades out to dark
+    #[builder(default = "20")]
+    pub fade_length: u16,
+    /// Tint drops depending on the category of each character
+    #[builder(default)]
+    pub color_by_category: bool,
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    pub seed: Option<u64>,
+    /// Avoid spawning drops in columns which already have a bright head
+    /// near the top, like on classic phosphor screens
+    #[builder(default)]
+    pub phosphor: bool,
+    /// Drops are moving along lanes, which are columns for vertical
+    /// directions and rows for horizontal ones
+    #[builder(default)]
+    pub direction: RainDirection,
+    /// Text revealed in the middle of the screen by drops passing through
+    #[builder(default)]
+    pub message: Option<String>,
+    /// Spawn drops in the lanes with fewer drops, so they don't clump
+    #[builder(default)]
+    pub column_balance: bool,
+    /// Part of brightness kept by cells drops passed through each frame,
+    /// leaves dim ghost trails, 0.0 clears them right away
+    #[builder(default)]
+    pub trail_persistence: f32,
+    /// Tint drops by position on screen instead of plain green
+    #[builder(default)]
+    pub hue_mode: HueMode,
+    /// Chance per frame to corrupt random rectangle of the screen with
+    /// scrambled or inverted cells, 0.0 for no glitches
+    #[builder(default)]
+    pub glitch_rate: f64,
+    /// Chance per update of each drop to freeze, frozen drop holds its
+    /// place and characters for a short while, 0.0 to never freeze
+    #[builder(default)]
+    pub freeze_chance: f64,
+    /// Text, like source code, drops are spelled from
+    #[builder(default)]
+    pub source: Option<SourceText>,
+    /// Number of frames the intro takes, drops number grows from one to
+    /// the max during it, 0 starts with full rain right away
+    #[builder(default)]
+    pub ramp_frames: u32,
 }
 
 pub struct DigitalRain {
@@ -20,37 +240,79 @@ pub struct DigitalRain {
     gradients: Vec<Vec<gradient::Color>>,
     rain_drops: Vec<RainDrop>,
     buffer: Buffer,
-    rng: rand::prelude::ThreadRng,
+    /// Next frame is drawn here and swapped with `buffer`, so no allocation
+    /// is needed for each frame
+    back_buffer: Buffer,
+    rng: EffectRng,
+    /// Multiplier of drops speed, changed by audio level in reactive mode
+    speed_factor: f32,
+    /// Number of updates since start, drives the intro
+    frame: u32,
 }
 
 impl TerminalEffect for DigitalRain {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    /// Lanes should be long enough for a drop with some trail
+    fn min_size(&self) -> (u16, u16) {
+        match self.options.direction {
+            RainDirection::Down | RainDirection::Up => (1, MIN_FALL_LENGTH),
+            RainDirection::Left | RainDirection::Right => (MIN_FALL_LENGTH, 1),
+        }
+    }
+
     /// Calculate difference between current frame and previous frame
     fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
-        let mut curr_buffer = Buffer::new(
-            self.options.get_width() as usize,
-            self.options.get_height() as usize,
-        );
+        let persistence = self.options.trail_persistence.clamp(0.0, 1.0);
+        if persistence > 0.0 {
+            decay_buffer(&self.buffer, &mut self.back_buffer, persistence);
+        } else {
+            self.back_buffer.clear();
+        }
 
         // fill current buffer
         // first draw drops with bigger fy
-        Self::fill_buffer(&mut self.rain_drops, &mut curr_buffer, &self.gradients);
+        Self::fill_buffer(
+            &mut self.rain_drops,
+            &mut self.back_buffer,
+            &self.gradients,
+            &self.options,
+            &mut self.rng,
+        );
 
-        let diff = self.buffer.diff(&curr_buffer);
-        self.buffer = curr_buffer;
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
         diff
     }
 
     /// Update each rain drop position
     fn update(&mut self) {
-        for rain_drop in self.rain_drops.iter_mut() {
-            rain_drop.update(
+        for index in 0..self.rain_drops.len() {
+            let fy = self.rain_drops[index].fy;
+            self.rain_drops[index].update(
                 &self.options,
-                Duration::from_millis(50),
+                Duration::from_millis((50.0 * self.speed_factor) as u64),
                 &mut self.rng,
             );
+            // drop moved back to the top, so it was reset
+            if self.rain_drops[index].fy < fy {
+                if let Some(lane) = self.spawn_lane() {
+                    self.rain_drops[index].fx = lane;
+                }
+            }
         }
 
-        self.add_one();
+        self.frame = self.frame.saturating_add(1);
+        if self.frame <= self.options.ramp_frames {
+            // intro, drops number follows the ramp
+            while self.rain_drops.len() < self.spawn_ramp() as usize {
+                self.push_drop();
+            }
+        } else {
+            self.add_one();
+        }
     }
 
     fn update_size(&mut self, width: u16, height: u16) {
@@ -58,29 +320,69 @@ impl TerminalEffect for DigitalRain {
     }
 
     fn reset(&mut self) {
-        let new_effect = DigitalRain::new(self.options.clone());
+        let new_effect =
+            DigitalRain::with_rng(self.options.clone(), self.rng.clone());
         *self = new_effect;
     }
+
+    /// Rain slows down in silence and runs twice faster on loud sound
+    fn set_intensity(&mut self, intensity: f32) {
+        self.speed_factor = 0.5 + 1.5 * intensity.clamp(0.0, 1.0);
+    }
 }
 
 /// Process digital rain effect.
 /// Noice that all processing done implying coordinates started from 0, 0
 /// and width / height is actual number of columnts and rows
 impl DigitalRain {
+    /// Printed by `tarts matrix --help`
+    pub fn help() -> &'static str {
+        "matrix: digital rain of katakana, digits and punctuation
+
+Options:
+  --density <d>     low, medium or high number of drops, medium by default
+                    keeps 120..240 drops on screen
+  --hue <mode>      green (default) or rainbow, rainbow changes hue across
+                    columns
+  --glitch          randomly corrupt blocks of the screen
+  --source <file>   drops spell text of the file instead of random
+                    characters
+  --reactive        drops speed up on loud sound, needs `audio` feature
+  --seed <n>        seed random generator
+
+Drops speed is 2..16 cells per second, capped on tall screens.
+"
+    }
+
     // Initialize screensaver
     pub fn new(options: DigitalRainOptions) -> Self {
-        let mut rng = rand::thread_rng();
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Initialize screensaver with provided random generator
+    pub fn with_rng(options: DigitalRainOptions, mut rng: EffectRng) -> Self {
         let mut rain_drops: Vec<RainDrop> = vec![];
-        let mut buffer: Buffer = Buffer::new(
+        let buffer: Buffer = Buffer::new(
             options.get_width() as usize,
             options.get_height() as usize,
         );
-        for rain_drop_id in 1..=options.get_min_drops_number() {
-            rain_drops.push(RainDrop::new(
-                &options,
-                rain_drop_id as usize,
-                &mut rng,
-            ));
+        // intro starts with a single drop
+        let initial_drops = match options.ramp_frames {
+            0 => options.get_min_drops_number(),
+            _ => options.get_min_drops_number().min(1),
+        };
+        for rain_drop_id in 1..=initial_drops {
+            let mut rain_drop =
+                RainDrop::new(&options, rain_drop_id as usize, &mut rng);
+            if options.column_balance {
+                rain_drop.fx = least_populated_lane(
+                    &rain_drops,
+                    options.get_lanes_number(),
+                    &mut rng,
+                );
+            }
+            rain_drops.push(rain_drop);
         }
 
         // fill gradients
@@ -98,7 +400,8 @@ impl DigitalRain {
                     b: 10,
                 },
                 4,
-                3 * options.get_height() as usize / 2,
+                3 * options.get_fall_length() as usize / 2,
+                true,
             ),
             gradient::two_step_color_gradient(
                 gradient::Color {
@@ -113,7 +416,8 @@ impl DigitalRain {
                     b: 10,
                 },
                 6,
-                3 * options.get_height() as usize / 2,
+                3 * options.get_fall_length() as usize / 2,
+                true,
             ),
             gradient::two_step_color_gradient(
                 gradient::Color {
@@ -127,19 +431,25 @@ impl DigitalRain {
                     g: 10,
                     b: 10,
                 },
-                options.get_height() as usize / 2,
-                3 * options.get_height() as usize / 2,
+                options.get_fall_length() as usize / 2,
+                3 * options.get_fall_length() as usize / 2,
+                true,
             ),
         ];
 
-        Self::fill_buffer(&mut rain_drops, &mut buffer, &gradients);
+        // buffer starts empty like the screen, so the first diff draws
+        // the whole frame
+        let back_buffer = buffer.clone();
 
         Self {
             options,
             gradients,
             rain_drops,
             buffer,
+            back_buffer,
             rng,
+            speed_factor: 1.0,
+            frame: 0,
         }
     }
 
@@ -147,43 +457,237 @@ impl DigitalRain {
         rain_drops: &mut [RainDrop],
         buffer: &mut Buffer,
         gradients: &[Vec<gradient::Color>],
+        options: &DigitalRainOptions,
+        rng: &mut EffectRng,
     ) {
         rain_drops.sort_by(|a, b| a.speed.partial_cmp(&b.speed).unwrap());
         for rain_drop in rain_drops.iter().rev() {
             let points = rain_drop.to_points_vec();
-            for (index, (x, y, character)) in points.iter().enumerate() {
-                let (width, height) = buffer.get_size();
-                if *x < width as u16 && *y < height as u16 {
-                    buffer.set(
-                        *x as usize,
-                        *y as usize,
-                        Cell::new(
-                            *character,
-                            pick_color(&rain_drop.style, index, gradients),
-                            pick_style(&rain_drop.style, index),
-                        ),
-                    );
+            for (index, (lane, position, character)) in points.iter().enumerate() {
+                if *lane < options.get_lanes_number()
+                    && *position < options.get_fall_length()
+                {
+                    let (x, y) = options.to_screen(*lane, *position);
+                    let cell = match options.message_glyph(*lane, *position) {
+                        Some(glyph) => {
+                            Cell::new(glyph, MESSAGE_COLOR, style::Attribute::Bold)
+                        }
+                        None => {
+                            let category = if options.color_by_category {
+                                char_category(*character)
+                            } else {
+                                None
+                            };
+                            Cell::new(
+                                *character,
+                                pick_color(
+                                    &rain_drop.style,
+                                    index,
+                                    x as usize,
+                                    gradients,
+                                    category,
+                                    options,
+                                ),
+                                pick_style(&rain_drop.style, index, options),
+                            )
+                        }
+                    };
+                    buffer.set(x as usize, y as usize, cell);
                 };
             }
         }
+
+        if options.glitch_rate > 0.0 && rng.gen_bool(options.glitch_rate.min(1.0)) {
+            glitch(buffer, rng);
+        }
     }
 
     /// Add one more worm with decent chance
     pub fn add_one(&mut self) {
-        if self.rain_drops.len() >= self.options.get_max_drops_number() as usize {
+        if self.rain_drops.len() >= self.spawn_ramp() as usize {
             return;
         };
-        let mut rng = rand::thread_rng();
-        if rng.gen_range(0.0..=1.0) <= 0.3 {
-            self.rain_drops.push(RainDrop::new(
-                &self.options,
-                self.rain_drops.len() + 1,
-                &mut rng,
+        if self.rng.gen_range(0.0..=1.0) <= 0.3 {
+            self.push_drop();
+        };
+    }
+
+    fn push_drop(&mut self) {
+        let mut rain_drop =
+            RainDrop::new(&self.options, self.rain_drops.len() + 1, &mut self.rng);
+        if let Some(lane) = self.spawn_lane() {
+            rain_drop.fx = lane;
+        }
+        self.rain_drops.push(rain_drop);
+    }
+
+    /// Most drops allowed at current frame, grows from one to the max
+    /// during intro
+    fn spawn_ramp(&self) -> u16 {
+        let max = self.options.get_max_drops_number();
+        if self.frame >= self.options.ramp_frames {
+            return max;
+        }
+        let ramped =
+            max as u64 * self.frame as u64 / self.options.ramp_frames as u64;
+        (ramped as u16).clamp(1.min(max), max)
+    }
+
+    /// Lane for new or reset drop if options ask for specific one, some of
+    /// drops carry message, with phosphor free lanes are preferred, with
+    /// column balance the least populated ones
+    fn spawn_lane(&mut self) -> Option<u16> {
+        let message_lanes = self.options.message_lanes();
+        if !message_lanes.is_empty() && self.rng.gen_bool(MESSAGE_DROPS_SHARE) {
+            return Some(message_lanes[self.rng.gen_range(0..message_lanes.len())]);
+        }
+        if self.options.phosphor {
+            return Some(self.pick_spawn_column());
+        }
+        if self.options.column_balance {
+            return Some(least_populated_lane(
+                &self.rain_drops,
+                self.options.get_lanes_number(),
+                &mut self.rng,
             ));
+        }
+        None
+    }
+
+    /// Lanes (columns for vertical rain) with drop head in the first quarter
+    /// of the way
+    fn busy_columns(&self) -> Vec<bool> {
+        let mut busy = vec![false; self.options.get_lanes_number() as usize];
+        let top = (self.options.get_fall_length() / 4) as f32;
+        for rain_drop in self.rain_drops.iter() {
+            if rain_drop.fy <= top {
+                if let Some(column) = busy.get_mut(rain_drop.fx as usize) {
+                    *column = true;
+                }
+            }
+        }
+        busy
+    }
+
+    /// Random column for the new drop, free columns are preferred
+    pub fn pick_spawn_column(&mut self) -> u16 {
+        let free: Vec<u16> = self
+            .busy_columns()
+            .iter()
+            .enumerate()
+            .filter(|(_, busy)| !**busy)
+            .map(|(column, _)| column as u16)
+            .collect();
+        match free.is_empty() {
+            true => self.rng.gen_range(0..self.options.get_lanes_number()),
+            false => free[self.rng.gen_range(0..free.len())],
+        }
+    }
+}
+
+/// Corrupt random rectangle, each cell is either inverted or replaced
+/// with scrambled character
+fn glitch(buffer: &mut Buffer, rng: &mut EffectRng) {
+    if buffer.width == 0 || buffer.height == 0 {
+        return;
+    }
+    let width = rng.gen_range(1..=MAX_GLITCH_SIZE.0.min(buffer.width));
+    let height = rng.gen_range(1..=MAX_GLITCH_SIZE.1.min(buffer.height));
+    let left = rng.gen_range(0..=buffer.width - width);
+    let top = rng.gen_range(0..=buffer.height - height);
+    for y in top..top + height {
+        for x in left..left + width {
+            let cell = buffer.get(x, y);
+            let glitched = match rng.gen_bool(0.5) {
+                true => Cell::new(
+                    cell.symbol,
+                    style::Color::Rgb { r: 0, g: 255, b: 0 },
+                    style::Attribute::Reverse,
+                ),
+                false => Cell::new(
+                    GLITCH_CHARACTERS[rng.gen_range(0..GLITCH_CHARACTERS.len())],
+                    style::Color::Rgb { r: 0, g: 255, b: 0 },
+                    style::Attribute::Bold,
+                ),
+            };
+            buffer.set(x, y, glitched);
+        }
+    }
+}
+
+/// Copy previous frame with every cell dimmed by persistence, too dark
+/// cells are cleared
+fn decay_buffer(previous: &Buffer, next: &mut Buffer, persistence: f32) {
+    for (cell, previous) in next.buffer.iter_mut().zip(previous.iter()) {
+        let (r, g, b) = color::to_rgb(previous.color);
+        let dim = |channel: u8| (channel as f32 * persistence) as u8;
+        let (r, g, b) = (dim(r), dim(g), dim(b));
+        *cell = match previous.symbol != ' ' && r.max(g).max(b) >= MIN_GHOST_LEVEL {
+            true => Cell::new(
+                previous.symbol,
+                style::Color::Rgb { r, g, b },
+                style::Attribute::Reset,
+            ),
+            false => Cell::default(),
         };
     }
 }
 
+/// Number of drops in each lane
+fn lanes_occupancy(rain_drops: &[RainDrop], lanes: u16) -> Vec<usize> {
+    let mut occupancy = vec![0; lanes as usize];
+    for rain_drop in rain_drops.iter() {
+        if let Some(count) = occupancy.get_mut(rain_drop.fx as usize) {
+            *count += 1;
+        }
+    }
+    occupancy
+}
+
+/// Random lane among ones with the smallest number of drops
+fn least_populated_lane(
+    rain_drops: &[RainDrop],
+    lanes: u16,
+    rng: &mut EffectRng,
+) -> u16 {
+    let occupancy = lanes_occupancy(rain_drops, lanes);
+    let Some(least) = occupancy.iter().min() else {
+        return 0;
+    };
+    let candidates: Vec<u16> = occupancy
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| *count == least)
+        .map(|(lane, _)| lane as u16)
+        .collect();
+    candidates[rng.gen_range(0..candidates.len())]
+}
+
+impl Default for DigitalRainOptions {
+    fn default() -> Self {
+        Self {
+            screen_size: (0, 0),
+            drops_range: (0, 0),
+            speed_range: (0, 0),
+            head_color: style::Color::White,
+            glow_length: 5,
+            fade_length: 20,
+            color_by_category: false,
+            seed: None,
+            phosphor: false,
+            direction: RainDirection::Down,
+            message: None,
+            column_balance: false,
+            trail_persistence: 0.0,
+            hue_mode: HueMode::Green,
+            glitch_rate: 0.0,
+            freeze_chance: 0.0,
+            source: None,
+            ramp_frames: 0,
+        }
+    }
+}
+
 impl DigitalRainOptions {
     #[inline]
     pub fn get_width(&self) -> u16 {
@@ -195,6 +699,85 @@ impl DigitalRainOptions {
         self.screen_size.1
     }
 
+    /// Number of lanes drops are falling along
+    #[inline]
+    pub fn get_lanes_number(&self) -> u16 {
+        match self.direction {
+            RainDirection::Down | RainDirection::Up => self.get_width(),
+            RainDirection::Left | RainDirection::Right => self.get_height(),
+        }
+    }
+
+    /// Length of the lane
+    #[inline]
+    pub fn get_fall_length(&self) -> u16 {
+        match self.direction {
+            RainDirection::Down | RainDirection::Up => self.get_height(),
+            RainDirection::Left | RainDirection::Right => self.get_width(),
+        }
+    }
+
+    /// Convert lane and position along it to screen coordinates, position
+    /// should be less than fall length
+    #[inline]
+    pub fn to_screen(&self, lane: u16, position: u16) -> (u16, u16) {
+        match self.direction {
+            RainDirection::Down => (lane, position),
+            RainDirection::Up => (lane, self.get_height() - 1 - position),
+            RainDirection::Right => (position, lane),
+            RainDirection::Left => (self.get_width() - 1 - position, lane),
+        }
+    }
+
+    /// Message is placed in the middle of the lanes, halfway through them
+    fn message_start(&self) -> Option<(u16, u16)> {
+        let length = self.message.as_ref()?.chars().count() as u16;
+        Some((
+            self.get_lanes_number().saturating_sub(length) / 2,
+            self.get_fall_length() / 2,
+        ))
+    }
+
+    /// Character of the message shown at lane and position, if any
+    pub fn message_glyph(&self, lane: u16, position: u16) -> Option<char> {
+        let (start, row) = self.message_start()?;
+        if position != row {
+            return None;
+        }
+        let index = lane.checked_sub(start)? as usize;
+        self.message
+            .as_ref()?
+            .chars()
+            .nth(index)
+            .filter(|glyph| !glyph.is_whitespace())
+    }
+
+    /// Lanes holding visible characters of the message
+    pub fn message_lanes(&self) -> Vec<u16> {
+        let (Some(message), Some((start, _))) =
+            (&self.message, self.message_start())
+        else {
+            return Vec::new();
+        };
+        message
+            .chars()
+            .enumerate()
+            .filter(|(_, glyph)| !glyph.is_whitespace())
+            .map(|(index, _)| start + index as u16)
+            .filter(|lane| *lane < self.get_lanes_number())
+            .collect()
+    }
+
+    #[inline]
+    pub fn get_glow_length(&self) -> usize {
+        self.glow_length as usize
+    }
+
+    #[inline]
+    pub fn get_fade_length(&self) -> usize {
+        self.fade_length.max(1) as usize
+    }
+
     #[inline]
     pub fn get_min_drops_number(&self) -> u16 {
         self.drops_range.0
@@ -207,18 +790,32 @@ impl DigitalRainOptions {
 
     #[inline]
     pub fn get_min_speed(&self) -> u16 {
-        self.speed_range.0
+        self.speed_range.0.min(self.get_max_speed())
     }
 
+    /// Upper bound of speed range, capped to keep drops readable
     #[inline]
     pub fn get_max_speed(&self) -> u16 {
-        self.speed_range.1
+        self.speed_range.1.min(MAX_SPEED)
+    }
+
+    /// Range of the length new drops grow up to, capped independent of
+    /// screen size so drops stay the same on tall terminals
+    pub fn get_drop_length_range(&self, initial: bool) -> (u16, u16) {
+        let fall_length = self.get_fall_length();
+        let (min, max) = match initial {
+            true => (4, 2 * fall_length / 3),
+            false => (fall_length / 4 + 1, fall_length / 2),
+        };
+        let max = max.min(MAX_DROP_LENGTH);
+        (min.min(max), max)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rain::rain_drop::RainDropStyle;
 
     fn get_sane_default_options() -> DigitalRainOptions {
         DigitalRainOptionsBuilder::default()
@@ -236,10 +833,234 @@ mod tests {
     }
 
     #[test]
-    fn no_diff() {
+    fn speed_and_length_capped_on_tall_screen() {
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((100, 300))
+            .drops_range((50, 60))
+            .speed_range((20, 150))
+            .seed(Some(1))
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options);
+        for _ in 0..200 {
+            rain.update();
+        }
+        for drop in rain.rain_drops.iter() {
+            assert!((20..=MAX_SPEED).contains(&drop.speed));
+            assert!(drop.max_length <= MAX_DROP_LENGTH as usize);
+        }
+    }
+
+    #[test]
+    fn density_scales_drops_range() {
+        assert_eq!(Density::default().drops_range((120, 240)), (120, 240));
+        assert_eq!("low".parse::<Density>(), Ok(Density::Low));
+        assert_eq!(Density::Low.drops_range((120, 240)), (60, 120));
+        assert_eq!(Density::High.drops_range((120, 240)), (240, 480));
+        assert!("dense".parse::<Density>().is_err());
+    }
+
+    #[test]
+    fn loud_sound_speeds_up_drops() {
+        let options = DigitalRainOptions {
+            seed: Some(7),
+            ..get_sane_default_options()
+        };
+        let mut quiet = DigitalRain::new(options.clone());
+        let mut loud = DigitalRain::new(options);
+        quiet.set_intensity(0.0);
+        loud.set_intensity(1.0);
+        quiet.update();
+        loud.update();
+        let travelled = |rain: &DigitalRain| -> f32 {
+            rain.rain_drops.iter().take(20).map(|drop| drop.fy).sum()
+        };
+        assert!(travelled(&loud) > travelled(&quiet));
+    }
+
+    #[test]
+    fn intro_ramps_drops_up() {
+        let options = DigitalRainOptions {
+            drops_range: (20, 40),
+            ramp_frames: 10,
+            ..get_sane_default_options()
+        };
+        let mut rain = DigitalRain::new(options.clone());
+        assert_eq!(rain.rain_drops.len(), 1);
+        for _ in 0..5 {
+            rain.update();
+        }
+        assert_eq!(rain.rain_drops.len(), 20);
+        for _ in 0..5 {
+            rain.update();
+        }
+        assert_eq!(rain.rain_drops.len(), 40);
+
+        // without ramp full rain starts right away
+        let rain = DigitalRain::new(DigitalRainOptions {
+            ramp_frames: 0,
+            ..options
+        });
+        assert_eq!(rain.rain_drops.len(), 20);
+    }
+
+    #[test]
+    fn same_seed_same_frames() {
+        let options = DigitalRainOptions {
+            seed: Some(42),
+            ..get_sane_default_options()
+        };
+        let mut first = DigitalRain::new(options.clone());
+        let mut second = DigitalRain::new(options);
+        for _ in 0..10 {
+            first.update();
+            second.update();
+            assert_eq!(first.get_diff(), second.get_diff());
+        }
+    }
+
+    #[test]
+    fn phosphor_spawns_in_free_columns() {
+        let options = DigitalRainOptions {
+            screen_size: (10, 40),
+            seed: Some(1),
+            phosphor: true,
+            ..get_sane_default_options()
+        };
+        let mut rain = DigitalRain::new(options);
+        rain.rain_drops = (0..5)
+            .map(|column| {
+                RainDrop::from_values(
+                    column as usize,
+                    vec!['a'],
+                    RainDropStyle::Front,
+                    column,
+                    2.0,
+                    10,
+                    10,
+                )
+            })
+            .collect();
+
+        for _ in 0..100 {
+            assert!(rain.pick_spawn_column() >= 5);
+        }
+    }
+
+    #[test]
+    fn column_balance_lowers_occupancy_variance() {
+        fn variance(rain: &DigitalRain) -> f32 {
+            let occupancy = lanes_occupancy(&rain.rain_drops, 40);
+            let mean = occupancy.iter().sum::<usize>() as f32 / 40.0;
+            occupancy
+                .iter()
+                .map(|count| (*count as f32 - mean).powi(2))
+                .sum::<f32>()
+                / 40.0
+        }
+
+        let options = DigitalRainOptions {
+            screen_size: (40, 40),
+            drops_range: (40, 60),
+            seed: Some(7),
+            ..get_sane_default_options()
+        };
+        let mut uniform = DigitalRain::new(options.clone());
+        let mut balanced = DigitalRain::new(DigitalRainOptions {
+            column_balance: true,
+            ..options
+        });
+        assert_eq!(variance(&balanced), 0.0);
+        for _ in 0..200 {
+            uniform.update();
+            balanced.update();
+        }
+        assert!(variance(&balanced) < variance(&uniform));
+    }
+
+    #[test]
+    fn ghost_trail_left_by_passed_drop() {
+        for (persistence, ghost) in [(0.0, false), (0.5, true)] {
+            let mut rain = DigitalRain::new(DigitalRainOptions {
+                screen_size: (10, 20),
+                trail_persistence: persistence,
+                ..get_sane_default_options()
+            });
+            rain.rain_drops = vec![RainDrop::from_values(
+                1,
+                vec!['a'; 3],
+                RainDropStyle::Front,
+                2,
+                5.0,
+                3,
+                10,
+            )];
+            let _ = rain.get_diff();
+            let passed = rain.buffer.get(2, 5);
+            assert_eq!(passed.symbol, 'a');
+
+            rain.rain_drops[0].fy = 15.0;
+            let _ = rain.get_diff();
+            let cell = rain.buffer.get(2, 5);
+            assert_eq!(cell.symbol != ' ', ghost);
+            if ghost {
+                let brightness = |c: Cell| {
+                    let (r, g, b) = color::to_rgb(c.color);
+                    r as u32 + g as u32 + b as u32
+                };
+                assert!(brightness(cell) < brightness(passed));
+            }
+        }
+    }
+
+    #[test]
+    fn first_diff_draws_whole_frame() {
         let mut foo = DigitalRain::new(get_sane_default_options());
         let q = foo.get_diff();
-        assert!(q.len() == 0);
+        assert!(!q.is_empty());
+        let drawn = foo.buffer.iter().filter(|c| **c != Cell::default()).count();
+        assert_eq!(q.len(), drawn);
+    }
+
+    #[test]
+    fn first_row_and_column_drawn() {
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((4, 12))
+            .drops_range((4, 4))
+            .speed_range((10, 20))
+            .seed(Some(2))
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options);
+        let (mut row, mut column) = (false, false);
+        for _ in 0..100 {
+            for (x, y, cell) in rain.get_diff() {
+                if cell.symbol != ' ' {
+                    row |= y == 0;
+                    column |= x == 0;
+                }
+            }
+            rain.update();
+        }
+        assert!(row && column);
+    }
+
+    #[test]
+    fn short_lanes_dont_panic() {
+        for height in 1..=5 {
+            let options = DigitalRainOptionsBuilder::default()
+                .screen_size((40, height))
+                .drops_range((20, 30))
+                .speed_range((10, 20))
+                .seed(Some(1))
+                .build()
+                .unwrap();
+            let mut rain = DigitalRain::new(options);
+            for _ in 0..20 {
+                rain.get_diff();
+                rain.update();
+            }
+        }
     }
 
     #[test]
@@ -247,6 +1068,183 @@ mod tests {
         let mut foo = DigitalRain::new(get_sane_default_options());
         foo.update();
         let q = foo.get_diff();
-        assert!(q.len() > 0)
+        assert!(!q.is_empty())
+    }
+
+    #[test]
+    fn double_buffer_same_diff_as_fresh_buffer() {
+        let mut rain = DigitalRain::new(get_sane_default_options());
+        for _ in 0..30 {
+            let previous = rain.buffer.clone();
+            let mut fresh = Buffer::new(100, 100);
+            // drops are only sorted by speed, which get_diff does anyway
+            DigitalRain::fill_buffer(
+                &mut rain.rain_drops,
+                &mut fresh,
+                &rain.gradients,
+                &rain.options,
+                &mut rain.rng.clone(),
+            );
+            assert_eq!(rain.get_diff(), previous.diff(&fresh));
+            rain.update();
+        }
+    }
+
+    #[test]
+    fn rain_up_moves_to_the_top() {
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((40, 30))
+            .drops_range((1, 1))
+            .speed_range((10, 10))
+            .direction(RainDirection::Up)
+            .build()
+            .unwrap();
+        let mut rng = make_rng(Some(1));
+        let mut drop = RainDrop::from_values(
+            1,
+            vec!['a'],
+            RainDropStyle::Back,
+            3,
+            5.0,
+            10,
+            10,
+        );
+
+        let mut last_y = options.to_screen(drop.fx, drop.to_point().1).1;
+        assert_eq!(last_y, 24);
+        for _ in 0..3 {
+            drop.update(&options, Duration::from_millis(500), &mut rng);
+            let (x, y) = options.to_screen(drop.fx, drop.to_point().1);
+            assert_eq!(x, 3);
+            assert!(y < last_y);
+            last_y = y;
+        }
+
+        // horizontal rain uses rows as lanes
+        let options = DigitalRainOptions {
+            direction: RainDirection::Left,
+            ..options
+        };
+        assert_eq!(options.get_lanes_number(), 30);
+        assert_eq!(options.to_screen(3, 0), (39, 3));
+    }
+
+    #[test]
+    fn message_revealed_by_passing_drops() {
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((20, 10))
+            .drops_range((1, 1))
+            .speed_range((10, 10))
+            .message(Some("H I".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(options.message_lanes(), vec![8, 10]);
+        assert_eq!(options.message_glyph(8, 5), Some('H'));
+        assert_eq!(options.message_glyph(9, 5), None);
+        assert_eq!(options.message_glyph(8, 4), None);
+
+        let rain = DigitalRain::new(options.clone());
+        let mut drops = vec![RainDrop::from_values(
+            1,
+            vec!['a'; 5],
+            RainDropStyle::Back,
+            8,
+            7.0,
+            5,
+            10,
+        )];
+        let mut buffer = Buffer::new(20, 10);
+        DigitalRain::fill_buffer(
+            &mut drops,
+            &mut buffer,
+            &rain.gradients,
+            &options,
+            &mut make_rng(Some(1)),
+        );
+        assert_eq!(buffer.get(8, 5).symbol, 'H');
+        assert_eq!(buffer.get(8, 5).color, MESSAGE_COLOR);
+        assert_eq!(buffer.get(8, 4).symbol, 'a');
+        // no drop in the lane, nothing revealed
+        assert_eq!(buffer.get(10, 5).symbol, ' ');
+    }
+
+    #[test]
+    fn glitches_appear_and_vanish() {
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((40, 20))
+            .drops_range((0, 0))
+            .speed_range((10, 10))
+            .glitch_rate(1.0)
+            .seed(Some(3))
+            .build()
+            .unwrap();
+        let mut rain = DigitalRain::new(options.clone());
+        let glitched = |rain: &DigitalRain| {
+            rain.buffer
+                .iter()
+                .filter(|c| **c != Cell::default())
+                .count()
+        };
+        for _ in 0..5 {
+            rain.get_diff();
+            assert!(glitched(&rain) > 0);
+            assert!(glitched(&rain) <= MAX_GLITCH_SIZE.0 * MAX_GLITCH_SIZE.1);
+        }
+
+        // without glitches empty rain draws nothing
+        let mut rain = DigitalRain::new(DigitalRainOptions {
+            glitch_rate: 0.0,
+            ..options
+        });
+        rain.get_diff();
+        assert_eq!(glitched(&rain), 0);
+    }
+
+    #[test]
+    fn options_serialize_to_config() {
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((80, 24))
+            .drops_range((20, 30))
+            .speed_range((2, 16))
+            .head_color(style::Color::Red)
+            .glow_length(3u16)
+            .fade_length(12u16)
+            .direction(RainDirection::Up)
+            .build()
+            .unwrap();
+        let config = serde_json::to_string(&options).unwrap();
+        assert!(config.contains("\"glow_length\":3"));
+        assert!(config.contains("\"direction\":\"up\""));
+        let loaded: DigitalRainOptions = serde_json::from_str(&config).unwrap();
+        assert_eq!(loaded, options);
+
+        // partial config gets the rest from defaults
+        let loaded: DigitalRainOptions =
+            serde_json::from_str("{\"fade_length\":7}").unwrap();
+        assert_eq!(loaded.fade_length, 7);
+        assert_eq!(loaded.glow_length, 5);
+    }
+
+    #[test]
+    fn message_and_source_round_trip() {
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((80, 24))
+            .drops_range((20, 30))
+            .speed_range((2, 16))
+            .message(Some("Wake up, Neo".to_string()))
+            .source(SourceText::new("fn main() {}"))
+            .hue_mode(HueMode::Rainbow)
+            .build()
+            .unwrap();
+        let config = serde_json::to_string(&options).unwrap();
+        assert!(config.contains("\"source\":\"fnmain(){}\""));
+        let loaded: DigitalRainOptions = serde_json::from_str(&config).unwrap();
+        assert_eq!(loaded, options);
+        assert_eq!(loaded.source.unwrap().next_char(), 'f');
+
+        assert!(
+            serde_json::from_str::<DigitalRainOptions>("{\"source\":\" \"}")
+                .is_err()
+        );
     }
 }
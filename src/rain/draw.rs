@@ -1,40 +1,62 @@
+use crate::color;
+use crate::rain::digital_rain::{DigitalRainOptions, HueMode};
 use crate::rain::gradient;
 use crate::rain::rain_drop::RainDropStyle;
 use crossterm::style;
 
-pub fn pick_style(vw_style: &RainDropStyle, pos: usize) -> style::Attribute {
+/// Fade length the hardcoded drop colors were tuned for
+const BASE_FADE_LENGTH: usize = 20;
+
+pub fn pick_style(
+    vw_style: &RainDropStyle,
+    pos: usize,
+    options: &DigitalRainOptions,
+) -> style::Attribute {
     match vw_style {
         RainDropStyle::Front => style::Attribute::Bold,
-        RainDropStyle::Middle => match pos {
-            0..=4 => style::Attribute::Bold,
-            _ => style::Attribute::NormalIntensity,
-        },
+        RainDropStyle::Middle => {
+            if pos < options.get_glow_length() {
+                style::Attribute::Bold
+            } else {
+                style::Attribute::NormalIntensity
+            }
+        }
         RainDropStyle::Back => style::Attribute::Bold,
         _ => style::Attribute::NormalIntensity,
     }
 }
 
+/// Green shade for the drop, the darker the bigger fade is
+#[inline]
+fn faded_green(fade: usize) -> style::Color {
+    style::Color::Rgb {
+        r: 0,
+        g: 255 - fade.clamp(10, 245) as u8,
+        b: 0,
+    }
+}
+
+/// Color of the drop cell at `pos` from the head, `x` is screen column
+/// of the cell used by hue modes
 pub fn pick_color(
     vw_style: &RainDropStyle,
     pos: usize,
+    x: usize,
     gradients: &[Vec<gradient::Color>],
+    category: Option<&str>,
+    options: &DigitalRainOptions,
 ) -> style::Color {
-    match vw_style {
+    let fade_length = options.get_fade_length();
+    let color = match vw_style {
         RainDropStyle::Gradient => match pos {
-            0 => style::Color::White,
-            _ => style::Color::Rgb {
-                r: 0,
-                g: 255 - (pos as u16 * 12).clamp(10, 256) as u8,
-                b: 0,
-            },
+            0 => options.head_color,
+            _ => faded_green(pos * 12 * BASE_FADE_LENGTH / fade_length),
         },
         RainDropStyle::Front => match pos {
-            0 => style::Color::White,
-            _ => style::Color::Rgb {
-                r: 0,
-                g: 255 - (pos.pow(2) as u16).clamp(10, 256) as u8,
-                b: 0,
-            },
+            0 => options.head_color,
+            _ => faded_green(
+                pos.pow(2) * BASE_FADE_LENGTH.pow(2) / fade_length.pow(2),
+            ),
         },
         RainDropStyle::Back => {
             let color = gradients[2][pos];
@@ -45,12 +67,69 @@ pub fn pick_color(
             }
         }
         _ => style::Color::DarkGrey,
+    };
+    let color = match options.hue_mode {
+        HueMode::Green => color,
+        HueMode::Rainbow => {
+            hue_by_column(color, x as f32 / options.get_width().max(1) as f32)
+        }
+    };
+
+    match category {
+        Some(category) => tint_by_category(color, category),
+        None => color,
+    }
+}
+
+/// Replace green shade with color of the hue keeping its brightness,
+/// named colors (like white head of the drop) are kept as is
+fn hue_by_column(color: style::Color, hue: f32) -> style::Color {
+    match color {
+        style::Color::Rgb { r, g, b } => {
+            let brightness = r.max(g).max(b) as u16;
+            let (r, g, b) = color::hue_to_rgb(hue);
+            let scale = |c: u8| (c as u16 * brightness / 255) as u8;
+            style::Color::Rgb {
+                r: scale(r),
+                g: scale(g),
+                b: scale(b),
+            }
+        }
+        _ => color,
+    }
+}
+
+/// Shift green shades a bit depending on the kind of character,
+/// named colors (like white head of the drop) are kept as is
+pub fn tint_by_category(color: style::Color, category: &str) -> style::Color {
+    match color {
+        style::Color::Rgb { r, g, b } => match category {
+            "digits" => style::Color::Rgb {
+                r,
+                g,
+                b: b.saturating_add(g / 2),
+            },
+            "punctuation" => style::Color::Rgb {
+                r: r.saturating_add(g / 3),
+                g,
+                b,
+            },
+            "other" => style::Color::Rgb {
+                r,
+                g,
+                b: b.saturating_add(g / 4),
+            },
+            _ => color,
+        },
+        _ => color,
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::rain::digital_rain::{DigitalRain, DigitalRainOptionsBuilder};
+    use crate::rain::rain_drop::char_category;
 
     // use crossterm::terminal;
     // use std::io::{self, Write};
@@ -65,6 +144,106 @@ mod tests {
         DigitalRain::new(rain_options)
     }
 
+    #[test]
+    fn color_by_category() {
+        let options = DigitalRainOptions::default();
+        let digit = pick_color(
+            &RainDropStyle::Gradient,
+            3,
+            0,
+            &[],
+            char_category('7'),
+            &options,
+        );
+        let katakana = pick_color(
+            &RainDropStyle::Gradient,
+            3,
+            0,
+            &[],
+            char_category('ﾊ'),
+            &options,
+        );
+        assert_ne!(digit, katakana);
+
+        // without category colors are the same
+        assert_eq!(
+            pick_color(&RainDropStyle::Gradient, 3, 0, &[], None, &options),
+            katakana
+        );
+    }
+
+    #[test]
+    fn default_trail_matches_original() {
+        let options = DigitalRainOptions::default();
+        for pos in 1..20 {
+            assert_eq!(
+                pick_color(&RainDropStyle::Gradient, pos, 0, &[], None, &options),
+                faded_green(pos * 12)
+            );
+            assert_eq!(
+                pick_color(&RainDropStyle::Front, pos, 0, &[], None, &options),
+                faded_green(pos.pow(2))
+            );
+        }
+        assert_eq!(
+            pick_style(&RainDropStyle::Middle, 4, &options),
+            style::Attribute::Bold
+        );
+        assert_eq!(
+            pick_style(&RainDropStyle::Middle, 5, &options),
+            style::Attribute::NormalIntensity
+        );
+    }
+
+    #[test]
+    fn custom_head_and_trail() {
+        let options = DigitalRainOptions {
+            head_color: style::Color::Red,
+            glow_length: 2,
+            fade_length: 40,
+            ..Default::default()
+        };
+        assert_eq!(
+            pick_color(&RainDropStyle::Front, 0, 0, &[], None, &options),
+            style::Color::Red
+        );
+        assert_eq!(
+            pick_color(&RainDropStyle::Gradient, 2, 0, &[], None, &options),
+            faded_green(12)
+        );
+        assert_eq!(
+            pick_style(&RainDropStyle::Middle, 2, &options),
+            style::Attribute::NormalIntensity
+        );
+    }
+
+    #[test]
+    fn rainbow_hue_by_column() {
+        let options = DigitalRainOptions {
+            screen_size: (30, 30),
+            hue_mode: HueMode::Rainbow,
+            ..Default::default()
+        };
+        let left = pick_color(&RainDropStyle::Gradient, 3, 0, &[], None, &options);
+        let right =
+            pick_color(&RainDropStyle::Gradient, 3, 20, &[], None, &options);
+        assert_ne!(left, right);
+        // first column is red as bright as green trail would be
+        assert_eq!(
+            left,
+            style::Color::Rgb {
+                r: 255 - 36,
+                g: 0,
+                b: 0
+            }
+        );
+        // head keeps its color
+        assert_eq!(
+            pick_color(&RainDropStyle::Front, 0, 20, &[], None, &options),
+            style::Color::White
+        );
+    }
+
     #[test]
     fn run_loop_10_iterations() {
         let mut stdout = Vec::new();
@@ -1,39 +1,172 @@
+/// Approximation of sRGB transfer function
+const GAMMA: f32 = 2.2;
+
 fn lerp(a: u8, b: u8, t: f32) -> u8 {
     (a as f32 * (1.0 - t) + b as f32 * t).round() as u8
 }
 
-#[derive(Clone, Copy)]
+/// Interpolate in linear light, so fades to black keep brightness longer
+/// instead of getting muddy in the middle
+fn lerp_gamma(a: u8, b: u8, t: f32) -> u8 {
+    let linear = |c: u8| (c as f32 / 255.0).powf(GAMMA);
+    let mixed = linear(a) * (1.0 - t) + linear(b) * t;
+    (mixed.max(0.0).powf(1.0 / GAMMA) * 255.0).round() as u8
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
 }
 
+fn lerp_color(a: Color, b: Color, t: f32, gamma: bool) -> Color {
+    let lerp = match gamma {
+        true => lerp_gamma,
+        false => lerp,
+    };
+    Color {
+        r: lerp(a.r, b.r, t),
+        g: lerp(a.g, b.g, t),
+        b: lerp(a.b, b.b, t),
+    }
+}
+
+/// Gradient of `length` colors through stops at given positions, stops
+/// should be sorted by position. Colors before the first stop and after
+/// the last one are colors of these stops. With `gamma` colors are mixed
+/// in linear light instead of raw sRGB values
+pub fn multi_step_gradient(
+    stops: &[(usize, Color)],
+    length: usize,
+    gamma: bool,
+) -> Vec<Color> {
+    assert!(!stops.is_empty(), "gradient needs at least one color stop");
+    (0..length)
+        .map(
+            |i| match stops.iter().position(|(position, _)| *position >= i) {
+                None => stops[stops.len() - 1].1,
+                Some(0) => stops[0].1,
+                Some(next) => {
+                    let (from, from_color) = stops[next - 1];
+                    let (to, to_color) = stops[next];
+                    let t = (i - from) as f32 / (to - from) as f32;
+                    lerp_color(from_color, to_color, t, gamma)
+                }
+            },
+        )
+        .collect()
+}
+
+/// Start color is left out, so the first one is already a step towards
+/// the middle, end color is reached at double distance of the middle point
 pub fn two_step_color_gradient(
     start_color: Color,
     middle_color: Color,
     end_color: Color,
     middle_point: usize,
     length: usize,
+    gamma: bool,
 ) -> Vec<Color> {
-    let mut gradient = vec![];
-    for i in 1..=length {
-        let (r, g, b) = if i <= middle_point {
-            let t = i as f32 / middle_point as f32;
-            (
-                lerp(start_color.r, middle_color.r, t),
-                lerp(start_color.g, middle_color.g, t),
-                lerp(start_color.b, middle_color.b, t),
-            )
-        } else {
-            let t = (i - middle_point) as f32 / middle_point as f32;
-            (
-                lerp(middle_color.r, end_color.r, t),
-                lerp(middle_color.g, end_color.g, t),
-                lerp(middle_color.b, end_color.b, t),
-            )
-        };
-        gradient.push(Color { r, g, b });
-    }
+    let mut gradient = multi_step_gradient(
+        &[
+            (0, start_color),
+            (middle_point, middle_color),
+            (2 * middle_point, end_color),
+        ],
+        length + 1,
+        gamma,
+    );
+    gradient.remove(0);
     gradient
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color { r: 0, g: 0, b: 0 };
+    const WHITE: Color = Color {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+    const GREEN: Color = Color { r: 0, g: 200, b: 0 };
+
+    #[test]
+    fn endpoints_are_stop_colors() {
+        let gradient =
+            multi_step_gradient(&[(2, WHITE), (6, GREEN), (10, BLACK)], 14, false);
+        assert_eq!(gradient.len(), 14);
+        // clamped before the first stop and after the last one
+        assert_eq!(gradient[0], WHITE);
+        assert_eq!(gradient[2], WHITE);
+        assert_eq!(gradient[6], GREEN);
+        assert_eq!(gradient[10], BLACK);
+        assert_eq!(gradient[13], BLACK);
+
+        assert_eq!(multi_step_gradient(&[(0, GREEN)], 3, false), vec![GREEN; 3]);
+    }
+
+    #[test]
+    fn intermediate_colors_interpolated() {
+        let gradient =
+            multi_step_gradient(&[(0, BLACK), (4, WHITE), (6, GREEN)], 7, false);
+        assert_eq!(
+            gradient[1],
+            Color {
+                r: 64,
+                g: 64,
+                b: 64
+            }
+        );
+        assert_eq!(
+            gradient[2],
+            Color {
+                r: 128,
+                g: 128,
+                b: 128
+            }
+        );
+        assert_eq!(
+            gradient[5],
+            Color {
+                r: 128,
+                g: 228,
+                b: 128
+            }
+        );
+    }
+
+    #[test]
+    fn two_step_skips_start_color() {
+        let gradient = two_step_color_gradient(BLACK, WHITE, GREEN, 2, 5, false);
+        assert_eq!(gradient.len(), 5);
+        assert_eq!(
+            gradient[0],
+            Color {
+                r: 128,
+                g: 128,
+                b: 128
+            }
+        );
+        assert_eq!(gradient[1], WHITE);
+        assert_eq!(gradient[3], GREEN);
+        assert_eq!(gradient[4], GREEN);
+    }
+
+    #[test]
+    fn gamma_midpoint_brighter_than_linear() {
+        assert_eq!(lerp(0, 255, 0.5), 128);
+        assert_eq!(lerp_gamma(0, 255, 0.5), 186);
+        // ends and equal colors are the same in both
+        assert_eq!(lerp_gamma(0, 255, 0.0), 0);
+        assert_eq!(lerp_gamma(0, 255, 1.0), 255);
+        assert_eq!(lerp_gamma(90, 90, 0.3), 90);
+
+        let linear = multi_step_gradient(&[(0, GREEN), (2, BLACK)], 3, false);
+        let gamma = multi_step_gradient(&[(0, GREEN), (2, BLACK)], 3, true);
+        assert_eq!((linear[0], linear[2]), (gamma[0], gamma[2]));
+        assert!(gamma[1].g > linear[1].g);
+    }
+}
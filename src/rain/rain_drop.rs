@@ -23,6 +23,9 @@ static CHARACTERS_MAP: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
     m
 });
 
+/// Range of updates frozen drop holds its position
+const FREEZE_UPDATES: (u16, u16) = (10, 40);
+
 /// Characters used to form kinda-canonical matrix effect
 static CHARACTERS: Lazy<Vec<char>> = Lazy::new(|| {
     let mut v = Vec::new();
@@ -32,6 +35,31 @@ static CHARACTERS: Lazy<Vec<char>> = Lazy::new(|| {
     v
 });
 
+/// Reverse lookup from character to its category label in `CHARACTERS_MAP`
+static CHARACTERS_CATEGORY: Lazy<HashMap<char, &str>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    for (category, chars) in CHARACTERS_MAP.iter() {
+        for character in chars.chars() {
+            m.insert(character, *category);
+        }
+    }
+    m
+});
+
+/// Next character of the drop body, taken from source text if options
+/// have one, random otherwise
+fn next_char<R: Rng + ?Sized>(options: &DigitalRainOptions, rng: &mut R) -> char {
+    match &options.source {
+        Some(source) => source.next_char(),
+        None => *CHARACTERS.choose(rng).unwrap(),
+    }
+}
+
+/// Get category label of character used in rain drops body
+pub fn char_category(character: char) -> Option<&'static str> {
+    CHARACTERS_CATEGORY.get(&character).copied()
+}
+
 pub enum RainDropStyle {
     Front,
     Middle,
@@ -48,6 +76,8 @@ pub struct RainDrop {
     pub fy: f32,
     pub max_length: usize,
     pub speed: u16,
+    /// Updates left until frozen drop starts falling again
+    pub frozen_frames: u16,
 }
 
 impl Distribution<RainDropStyle> for Standard {
@@ -66,25 +96,26 @@ impl Distribution<RainDropStyle> for Standard {
 /// Set of operations to make drain drop moving and growing
 impl RainDrop {
     /// Create new rain drop with sane random defaults
-    pub fn new(
+    pub fn new<R: Rng + ?Sized>(
         options: &DigitalRainOptions,
         drop_id: usize,
-        rng: &mut rand::prelude::ThreadRng,
+        rng: &mut R,
     ) -> Self {
         // pick random first character
-        let style: RainDropStyle = rand::random();
-        let fx: u16 = rng.gen_range(0..options.get_width());
-        let fy: f32 = rng.gen_range(0..options.get_height() / 4) as f32;
-        let max_length: usize =
-            rng.gen_range(4..=(2 * options.get_height() / 3)) as usize;
+        let style: RainDropStyle = rng.gen();
+        let fx: u16 = rng.gen_range(0..options.get_lanes_number());
+        let fy: f32 =
+            rng.gen_range(0..(options.get_fall_length() / 4).max(1)) as f32;
+        let (min_length, max_length) = options.get_drop_length_range(true);
+        let max_length = rng.gen_range(min_length..=max_length) as usize;
 
         let speed: u16 =
             rng.gen_range(options.get_min_speed()..=options.get_max_speed());
 
-        let init_length = rng.gen_range(1..max_length / 2);
-        let mut body: Vec<char> = vec![*CHARACTERS.choose(rng).unwrap()];
+        let init_length = rng.gen_range(1..(max_length / 2).max(2));
+        let mut body: Vec<char> = vec![next_char(options, rng)];
         for _ in 1..init_length {
-            body.push(*CHARACTERS.choose(rng).unwrap());
+            body.push(next_char(options, rng));
         }
 
         Self::from_values(drop_id, body, style, fx, fy, max_length, speed)
@@ -109,6 +140,7 @@ impl RainDrop {
             fy,
             max_length,
             speed,
+            frozen_frames: 0,
         }
     }
 
@@ -120,7 +152,9 @@ impl RainDrop {
         (x, y)
     }
 
-    /// Receive vector of coordinates of RainDrop body
+    /// Receive vector of coordinates of RainDrop body, x is the lane and y is
+    /// position along it, `DigitalRainOptions::to_screen` maps them to
+    /// the screen for the rain direction
     pub fn to_points_vec(&self) -> Vec<(u16, u16, char)> {
         let mut points = vec![];
         let (head_x, head_y) = self.to_point();
@@ -136,21 +170,21 @@ impl RainDrop {
     }
 
     /// Reset worm to the sane defaults
-    fn reset(
+    fn reset<R: Rng + ?Sized>(
         &mut self,
         options: &DigitalRainOptions,
-        rng: &mut rand::prelude::ThreadRng,
+        rng: &mut R,
     ) {
         self.body.clear();
-        self.body.insert(0, *CHARACTERS.choose(rng).unwrap());
-        self.style = rand::random();
+        self.body.insert(0, next_char(options, rng));
+        self.style = rng.gen();
         self.fy = 0.0;
-        self.fx = rng.gen_range(0..options.get_width());
+        self.fx = rng.gen_range(0..options.get_lanes_number());
         self.speed =
             rng.gen_range(options.get_min_speed()..=options.get_max_speed());
-        self.max_length = rng
-            .gen_range(options.get_height() / 4 + 1..=(options.get_height() / 2))
-            as usize;
+        let (min_length, max_length) = options.get_drop_length_range(false);
+        self.max_length = rng.gen_range(min_length..=max_length) as usize;
+        self.frozen_frames = 0;
     }
 
     /// Grow condition
@@ -159,7 +193,12 @@ impl RainDrop {
     }
 
     /// Grow up matrix worm characters array
-    fn grow(&mut self, head_y: u16, rng: &mut rand::prelude::ThreadRng) {
+    fn grow<R: Rng + ?Sized>(
+        &mut self,
+        head_y: u16,
+        options: &DigitalRainOptions,
+        rng: &mut R,
+    ) {
         if self.body.len() >= self.max_length {
             self.body.truncate(self.max_length);
             return;
@@ -171,7 +210,7 @@ impl RainDrop {
                 let delta: i16 = head_y as i16 - self.fy.round() as i16;
                 if delta > 0 {
                     for _ in 0..delta as usize {
-                        self.body.insert(0, *CHARACTERS.choose(rng).unwrap());
+                        self.body.insert(0, next_char(options, rng));
                     }
                 };
             }
@@ -179,7 +218,7 @@ impl RainDrop {
                 // grow only to one character if position changed
                 let delta: i16 = head_y as i16 - self.fy.round() as i16;
                 if delta > 0 {
-                    self.body.insert(0, *CHARACTERS.choose(rng).unwrap());
+                    self.body.insert(0, next_char(options, rng));
                 };
             }
         };
@@ -188,7 +227,7 @@ impl RainDrop {
     }
 
     /// Update rain drops to change position/grow etc
-    /// there can be 4 cases:
+    /// there can be 4 cases, first two grow the drop:
     /// rain drop vector not yet fully come from top
     /// rain drop vector somewhere in the middle of the scren
     /// rain drop vector reach bottom and need to fade out
@@ -196,11 +235,11 @@ impl RainDrop {
     ///
     /// Note that rain drop coordiantes can be outside bounds defined
     /// by screen width and height, this should be handled during draw process
-    pub fn update(
+    pub fn update<R: Rng + ?Sized>(
         &mut self,
         options: &DigitalRainOptions,
         dt: Duration,
-        rng: &mut rand::prelude::ThreadRng,
+        rng: &mut R,
     ) {
         // NOTE: looks like guard, but why i even need it here?
         if self.body.is_empty() {
@@ -208,44 +247,47 @@ impl RainDrop {
             return;
         }
 
+        // frozen drop holds its place and characters for a while
+        if self.frozen_frames > 0 {
+            self.frozen_frames -= 1;
+            return;
+        }
+        if options.freeze_chance > 0.0
+            && rng.gen_bool(options.freeze_chance.min(1.0))
+        {
+            self.frozen_frames = rng.gen_range(FREEZE_UPDATES.0..=FREEZE_UPDATES.1);
+            return;
+        }
+
         // new fy coordinate
         let fy = self.fy + (self.speed as f32 * dt.as_millis() as f32) / 1000.0;
 
         // calculate head and tail y coordinate
-        let head_y = fy.round() as u16;
-        let tail_y = fy.round() as i16 - self.body.len() as i16;
-        let height = options.get_height();
-
-        if tail_y <= 0 {
-            // not fully come out from top
-            self.grow(head_y, rng);
-            self.fy = fy;
-            return;
-        };
-
-        if (head_y <= height) && (tail_y > 0) {
-            // somewhere in the middle
-            self.grow(head_y, rng);
-            self.fy = fy;
-            return;
-        };
+        let head_y = fy.round() as i16;
+        let tail_y = head_y - self.body.len() as i16;
+        let height = options.get_fall_length() as i16;
 
-        if (head_y > height) && (tail_y < height as i16) {
-            // got to the bottom
-            self.fy = fy;
+        if tail_y >= height {
+            // tail is out of the screen, start again from the top
+            self.reset(options, rng);
             return;
         };
 
-        // NOTE: need this to reset
-        if tail_y as u16 >= height {
-            self.reset(options, rng);
+        if head_y <= height {
+            // not fully come out from top or somewhere in the middle
+            self.grow(head_y as u16, options, rng);
         };
+        // otherwise got to the bottom and fading out
+        self.fy = fy;
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{super::digital_rain::DigitalRainOptionsBuilder, *};
+    use super::{
+        super::digital_rain::{DigitalRainOptionsBuilder, SourceText},
+        *,
+    };
 
     fn get_sane_options() -> DigitalRainOptions {
         DigitalRainOptionsBuilder::default()
@@ -260,7 +302,7 @@ mod tests {
     fn create_new_and_reset() {
         let mut rng = rand::thread_rng();
         let mut new_drop = RainDrop::new(&get_sane_options(), 1, &mut rng);
-        assert!(new_drop.body.len() > 0);
+        assert!(!new_drop.body.is_empty());
         assert!(new_drop.speed > 0);
 
         new_drop.reset(&get_sane_options(), &mut rng);
@@ -279,6 +321,14 @@ mod tests {
         assert_eq!(drops.len(), 1000);
     }
 
+    #[test]
+    fn categories() {
+        assert_eq!(char_category('1'), Some("digits"));
+        assert_eq!(char_category('ﾊ'), Some("katakana"));
+        assert_eq!(char_category('+'), Some("punctuation"));
+        assert_eq!(char_category('Z'), None);
+    }
+
     #[test]
     fn to_point() {
         let new_drop = RainDrop::from_values(
@@ -314,6 +364,7 @@ mod tests {
     #[test]
     fn grow() {
         let mut rng = rand::thread_rng();
+        let options = get_sane_options();
         let mut new_drop = RainDrop::from_values(
             1,
             vec!['a'],
@@ -323,9 +374,9 @@ mod tests {
             20,
             10,
         );
-        new_drop.grow(10, &mut rng);
+        new_drop.grow(10, &options, &mut rng);
         assert_eq!(new_drop.body.len(), 1);
-        assert_eq!(new_drop.body.get(0), Some(&'a'));
+        assert_eq!(new_drop.body.first(), Some(&'a'));
 
         let mut new_drop = RainDrop::from_values(
             1,
@@ -336,10 +387,10 @@ mod tests {
             20,
             4,
         );
-        new_drop.grow(12, &mut rng);
+        new_drop.grow(12, &options, &mut rng);
         assert_eq!(new_drop.body.len(), 2);
         assert_eq!(new_drop.body.get(1), Some(&'b'));
-        new_drop.grow(11, &mut rng);
+        new_drop.grow(11, &options, &mut rng);
         assert_eq!(new_drop.body.len(), 2);
 
         let mut new_drop = RainDrop::from_values(
@@ -352,11 +403,37 @@ mod tests {
             4,
         );
         for _ in 1..10 {
-            new_drop.grow(12, &mut rng);
+            new_drop.grow(12, &options, &mut rng);
         }
         assert_eq!(new_drop.body.len(), 3);
     }
 
+    #[test]
+    fn body_spelled_from_source() {
+        let mut rng = rand::thread_rng();
+        let options = DigitalRainOptions {
+            source: SourceText::new("fn main()\n"),
+            ..get_sane_options()
+        };
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['x'],
+            RainDropStyle::Middle,
+            10,
+            10.0,
+            20,
+            4,
+        );
+        for head_y in 11..=20 {
+            new_drop.grow(head_y, &options, &mut rng);
+            new_drop.fy = head_y as f32;
+        }
+        // head is the newest character, whitespace is skipped and text
+        // starts over when it ends
+        let body: String = new_drop.body.iter().rev().collect();
+        assert_eq!(body, "xfnmain()fn");
+    }
+
     #[test]
     fn update() {
         let mut rng = rand::thread_rng();
@@ -408,7 +485,7 @@ mod tests {
         );
         new_drop.update(&get_sane_options(), Duration::from_millis(1000), &mut rng);
         assert_eq!(new_drop.body.len(), 5);
-        assert_eq!(new_drop.fy > 30.0, true);
+        assert!(new_drop.fy > 30.0);
 
         // when head_y > screen height and body len is 2
         let mut new_drop = RainDrop::from_values(
@@ -427,6 +504,68 @@ mod tests {
         assert_eq!(new_drop.fy, 33.0); // should be reseted there
     }
 
+    #[test]
+    fn frozen_drop_holds_position() {
+        let mut rng = rand::thread_rng();
+        let options = DigitalRainOptions {
+            freeze_chance: 1.0,
+            ..get_sane_options()
+        };
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b'],
+            RainDropStyle::Back,
+            10,
+            10.0,
+            5,
+            10,
+        );
+        new_drop.update(&options, Duration::from_millis(1000), &mut rng);
+        assert!(new_drop.frozen_frames >= FREEZE_UPDATES.0);
+        let frozen = new_drop.frozen_frames;
+        for _ in 0..frozen {
+            new_drop.update(&options, Duration::from_millis(1000), &mut rng);
+        }
+        assert_eq!(new_drop.fy, 10.0);
+        assert_eq!(new_drop.body, vec!['a', 'b']);
+
+        // falls again once thawed
+        new_drop.update(&get_sane_options(), Duration::from_millis(1000), &mut rng);
+        assert_eq!(new_drop.fy, 20.0);
+    }
+
+    #[test]
+    fn reset_after_tail_left_bottom() {
+        let mut rng = rand::thread_rng();
+        let options = DigitalRainOptionsBuilder::default()
+            .screen_size((10, 10))
+            .drops_range((1, 1))
+            .speed_range((10, 10))
+            .build()
+            .unwrap();
+        let mut new_drop = RainDrop::from_values(
+            1,
+            vec!['a', 'b', 'c'],
+            RainDropStyle::Back,
+            5,
+            9.0,
+            3,
+            10,
+        );
+        let mut fy = new_drop.fy;
+        for _ in 0..10 {
+            new_drop.update(&options, Duration::from_millis(100), &mut rng);
+            if new_drop.fy < fy {
+                break;
+            }
+            fy = new_drop.fy;
+        }
+        // head passed the bottom and tail followed it before reset
+        assert!(fy >= 12.0);
+        assert!(new_drop.fy <= 1.0);
+        assert_eq!(new_drop.body.len(), 1);
+    }
+
     #[test]
     fn out_of_bounds() {
         let mut rng = rand::thread_rng();
@@ -0,0 +1,318 @@
+//! Recording of effect frames to animated GIF. Every cell is rasterized to
+//! a block of pixels, there is no font bundled, so only blocks and braille
+//! dots are drawn exactly, other characters get a pseudo glyph generated
+//! from their code point, which is good enough for previews.
+use crate::braille::{braille_bits, BRAILLE_DOTS};
+use crate::buffer::Buffer;
+use crate::check::capture_frames;
+use crate::color;
+use crate::common::TerminalEffect;
+use derive_builder::Builder;
+use std::{fs::File, io, path::Path, time::Duration};
+
+/// Pseudo glyph is drawn on grid of 3 columns and 5 rows
+const GLYPH_GRID: (usize, usize) = (3, 5);
+
+#[derive(Builder, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct RecordOptions {
+    /// Size of one terminal cell in pixels
+    #[builder(default = "(8, 16)")]
+    cell_size: (u16, u16),
+    /// Frames per second of resulting animation
+    #[builder(default = "30")]
+    fps: u16,
+    #[builder(default = "(0, 0, 0)")]
+    background: (u8, u8, u8),
+}
+
+impl Default for RecordOptions {
+    fn default() -> Self {
+        RecordOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// Writer of asciinema v2 cast file. Everything written between flushes
+/// becomes one output event, `run_loop` flushes once per frame, so each
+/// frame is stamped with its time on the target frame rate
+pub struct CastWriter<W: io::Write> {
+    out: W,
+    frame: Vec<u8>,
+    time: Duration,
+    frame_duration: Duration,
+}
+
+impl<W: io::Write> CastWriter<W> {
+    pub fn new(
+        mut out: W,
+        screen_size: (u16, u16),
+        frame_duration: Duration,
+    ) -> io::Result<Self> {
+        writeln!(
+            out,
+            r#"{{"version": 2, "width": {}, "height": {}}}"#,
+            screen_size.0, screen_size.1
+        )?;
+        // player starts with visible cursor and whatever on the screen
+        let frame = b"\x1b[?25l\x1b[2J".to_vec();
+        Ok(Self {
+            out,
+            frame,
+            time: Duration::ZERO,
+            frame_duration,
+        })
+    }
+}
+
+impl<W: io::Write> io::Write for CastWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.frame.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.frame.is_empty() {
+            writeln!(
+                self.out,
+                r#"[{:.6}, "o", "{}"]"#,
+                self.time.as_secs_f64(),
+                json_escape(&String::from_utf8_lossy(&self.frame))
+            )?;
+            self.frame.clear();
+        }
+        self.time += self.frame_duration;
+        self.out.flush()
+    }
+}
+
+/// Escape string to be put inside of JSON string literal
+fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\u{7f}' => {
+                escaped.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Run effect for given number of frames and write them as GIF to the file
+pub fn record_gif<T: TerminalEffect>(
+    effect: &mut T,
+    screen_size: (u16, u16),
+    frames: usize,
+    path: &Path,
+    options: &RecordOptions,
+) -> io::Result<()> {
+    let frames = capture_frames(effect, screen_size, frames);
+    write_gif(File::create(path)?, &frames, options)
+}
+
+/// Encode frames to GIF looped forever
+pub fn write_gif<W: io::Write>(
+    out: W,
+    frames: &[Buffer],
+    options: &RecordOptions,
+) -> io::Result<()> {
+    let Some(first) = frames.first() else {
+        return Ok(());
+    };
+    let (width, height) = pixel_size(first, options);
+    let mut encoder =
+        gif::Encoder::new(out, width, height, &[]).map_err(io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+
+    // delay is set in hundredths of second
+    let delay = (100 / options.fps.max(1)).max(2);
+    for buffer in frames {
+        let pixels = rasterize(buffer, options);
+        let mut frame = gif::Frame::from_rgb_speed(width, height, &pixels, 10);
+        frame.delay = delay;
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+fn pixel_size(buffer: &Buffer, options: &RecordOptions) -> (u16, u16) {
+    (
+        (buffer.width * options.cell_size.0 as usize).min(u16::MAX as usize) as u16,
+        (buffer.height * options.cell_size.1 as usize).min(u16::MAX as usize)
+            as u16,
+    )
+}
+
+/// Draw buffer to rgb pixels, 3 bytes per pixel, rows top to bottom
+pub fn rasterize(buffer: &Buffer, options: &RecordOptions) -> Vec<u8> {
+    let (width, height) = pixel_size(buffer, options);
+    let (width, height) = (width as usize, height as usize);
+    let (cell_width, cell_height) =
+        (options.cell_size.0 as usize, options.cell_size.1 as usize);
+    let (br, bg, bb) = options.background;
+    let mut pixels = [br, bg, bb].repeat(width * height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let cell = buffer.get(x / cell_width, y / cell_height);
+            let (px, py) = (x % cell_width, y % cell_height);
+            if glyph_pixel(cell.symbol, px, py, (cell_width, cell_height)) {
+                let (r, g, b) = color::to_rgb(cell.color);
+                let offset = (y * width + x) * 3;
+                pixels[offset..offset + 3].copy_from_slice(&[r, g, b]);
+            }
+        }
+    }
+    pixels
+}
+
+/// Whether pixel (px, py) of the cell is lit for given character
+pub fn glyph_pixel(
+    symbol: char,
+    px: usize,
+    py: usize,
+    cell_size: (usize, usize),
+) -> bool {
+    let (cell_width, cell_height) = cell_size;
+    match symbol {
+        ' ' => false,
+        '█' => true,
+        _ => {
+            if let Some(bits) = braille_bits(symbol) {
+                // dot takes middle half of its 2x4 slot
+                let (slot_width, slot_height) =
+                    ((cell_width / 2).max(1), (cell_height / 4).max(1));
+                let (dot_x, dot_y) = (px / slot_width, py / slot_height);
+                let (in_x, in_y) = (px % slot_width, py % slot_height);
+                return dot_x < 2
+                    && dot_y < 4
+                    && bits & BRAILLE_DOTS[dot_y][dot_x] != 0
+                    && in_x * 4 >= slot_width
+                    && in_x * 4 < slot_width * 3
+                    && in_y * 4 >= slot_height
+                    && in_y * 4 < slot_height * 3;
+            }
+
+            // pseudo glyph inside of one pixel margin, mirrored like letters
+            if px == 0 || py == 0 || px + 1 >= cell_width || py + 1 >= cell_height {
+                return false;
+            }
+            let (grid_width, grid_height) = GLYPH_GRID;
+            let gx = (px - 1) * grid_width / (cell_width - 2).max(1);
+            let gy = (py - 1) * grid_height / (cell_height - 2).max(1);
+            let column = gx.min(grid_width - 1 - gx);
+            let pattern = (symbol as u32).wrapping_mul(2_654_435_761) >> 12;
+            pattern & (1 << (gy * 2 + column)) != 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Cell;
+    use crossterm::style;
+
+    fn options() -> RecordOptions {
+        RecordOptionsBuilder::default()
+            .cell_size((4_u16, 8_u16))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn block_fills_cell_with_color() {
+        let mut buffer = Buffer::new(2, 1);
+        buffer.set(
+            1,
+            0,
+            Cell::new('█', style::Color::Red, style::Attribute::Reset),
+        );
+        let pixels = rasterize(&buffer, &options());
+        assert_eq!(pixels.len(), 8 * 8 * 3);
+        // first cell is background, second one is red
+        assert_eq!(&pixels[0..3], &[0, 0, 0]);
+        assert_eq!(&pixels[4 * 3..4 * 3 + 3], &[255, 0, 0]);
+        assert_eq!(&pixels[(7 * 8 + 7) * 3..], &[255, 0, 0]);
+    }
+
+    #[test]
+    fn braille_dots_drawn_in_their_slots() {
+        // only top left and bottom right dots
+        let symbol = crate::braille::braille_char(0x01 | 0x80);
+        let lit: Vec<(usize, usize)> = (0..8)
+            .flat_map(|py| (0..4).map(move |px| (px, py)))
+            .filter(|&(px, py)| glyph_pixel(symbol, px, py, (4, 8)))
+            .collect();
+        assert_eq!(lit, vec![(1, 1), (3, 7)]);
+        assert!(!glyph_pixel(' ', 1, 1, (4, 8)));
+    }
+
+    #[test]
+    fn gif_written() {
+        let mut buffer = Buffer::new(3, 2);
+        buffer.set(
+            0,
+            0,
+            Cell::new('A', style::Color::Green, style::Attribute::Reset),
+        );
+        let mut out = Vec::new();
+        write_gif(&mut out, &[buffer.clone(), buffer], &options()).unwrap();
+        assert!(out.starts_with(b"GIF89a"));
+    }
+
+    #[test]
+    fn first_matrix_frame_not_blank() {
+        use crate::rain::digital_rain::{DigitalRain, DigitalRainOptionsBuilder};
+
+        let rain_options = DigitalRainOptionsBuilder::default()
+            .screen_size((30, 8))
+            .drops_range((10, 20))
+            .speed_range((2, 16))
+            .seed(Some(1))
+            .build()
+            .unwrap();
+        let frames =
+            capture_frames(&mut DigitalRain::new(rain_options), (30, 8), 2);
+        assert!(frames[0].iter().any(|c| c.symbol != ' '));
+        let pixels = rasterize(&frames[0], &options());
+        assert!(pixels.iter().any(|channel| *channel != 0));
+    }
+
+    #[test]
+    fn cast_event_per_flush() {
+        use std::io::Write;
+
+        let mut out = Vec::new();
+        let mut cast =
+            CastWriter::new(&mut out, (80, 24), Duration::from_millis(500))
+                .unwrap();
+        cast.flush().unwrap();
+        cast.write_all("\x1b[1;1H\"ﾊ\"".as_bytes()).unwrap();
+        cast.flush().unwrap();
+        // nothing drawn in this frame, only time goes
+        cast.flush().unwrap();
+        cast.write_all(b"\\").unwrap();
+        cast.flush().unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"version": 2, "width": 80, "height": 24}"#,
+                r#"[0.000000, "o", "\u001b[?25l\u001b[2J"]"#,
+                r#"[0.500000, "o", "\u001b[1;1H\"ﾊ\""]"#,
+                r#"[1.500000, "o", "\\"]"#,
+            ]
+        );
+    }
+}
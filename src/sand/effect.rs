@@ -0,0 +1,295 @@
+//! Falling sand, grains are poured from spouts wandering along the top of
+//! the screen. Each update grains fall to empty cell below, or slide down
+//! diagonally, so they pile up in slopes. Grid is the buffer itself, which
+//! is mutated in place instead of being repainted every frame.
+use crate::buffer::{Buffer, Cell};
+use crate::common::{make_rng, EffectRng, TerminalEffect};
+use crossterm::style;
+use derive_builder::Builder;
+use rand::{seq::SliceRandom, Rng};
+
+const GRAIN_CHAR: char = '█';
+
+/// Pile is cleared when this part of the top row is covered
+const FULL_TOP_ROW: f32 = 0.5;
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct SandOptions {
+    screen_size: (u16, u16),
+    /// Number of grains poured each frame, fractional part is a chance
+    /// of one more grain
+    #[builder(default = "3.0")]
+    spawn_rate: f32,
+    /// Colors of grains, there is one spout for each color
+    #[builder(default = "default_colors()")]
+    colors: Vec<style::Color>,
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    seed: Option<u64>,
+}
+
+fn default_colors() -> Vec<style::Color> {
+    vec![
+        style::Color::Rgb {
+            r: 237,
+            g: 201,
+            b: 120,
+        },
+        style::Color::Rgb {
+            r: 214,
+            g: 160,
+            b: 90,
+        },
+        style::Color::Rgb {
+            r: 190,
+            g: 120,
+            b: 70,
+        },
+    ]
+}
+
+pub struct Sand {
+    options: SandOptions,
+    /// Last drawn frame
+    buffer: Buffer,
+    /// Grains on the screen, empty cells are default ones
+    grid: Buffer,
+    /// Column and color of each spout
+    spouts: Vec<(usize, style::Color)>,
+    rng: EffectRng,
+    /// Scan direction along the row, switched each update so grains don't
+    /// slide to one side only
+    scan_left: bool,
+}
+
+impl TerminalEffect for Sand {
+    fn name(&self) -> &'static str {
+        "sand"
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        let diff = self.buffer.diff(&self.grid);
+        self.buffer.clone_from(&self.grid);
+        diff
+    }
+
+    fn update(&mut self) {
+        self.settle();
+        if self.top_row_full() {
+            self.grid.clear();
+        }
+        self.wander_spouts();
+        self.pour();
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        let new_effect = Self::with_rng(self.options.clone(), self.rng.clone());
+        *self = new_effect;
+    }
+
+    /// Grains inside new screen are kept, spouts moved in if left outside
+    fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        let (width, height) = (width as usize, height as usize);
+        self.grid.resize(width, height);
+        self.buffer = Buffer::new(width, height);
+        for (x, _) in self.spouts.iter_mut() {
+            *x %= width;
+        }
+    }
+}
+
+impl Sand {
+    /// Printed by `tarts sand --help`
+    pub fn help() -> &'static str {
+        "sand: falling sand poured from spouts wandering along the top
+
+Options:
+  --seed <n>        seed random generator
+"
+    }
+
+    pub fn new(options: SandOptions) -> Self {
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Create effect with provided random generator
+    pub fn with_rng(options: SandOptions, mut rng: EffectRng) -> Self {
+        let buffer = Buffer::new(
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        let spouts = options
+            .colors
+            .iter()
+            .map(|color| (rng.gen_range(0..buffer.width), *color))
+            .collect();
+
+        Self {
+            options,
+            grid: buffer.clone(),
+            buffer,
+            spouts,
+            rng,
+            scan_left: false,
+        }
+    }
+
+    fn is_empty(&self, x: usize, y: usize) -> bool {
+        self.grid.get(x, y).symbol == ' '
+    }
+
+    /// Move every grain one cell down or diagonally down, rows are scanned
+    /// from the bottom so each grain moves once
+    pub fn settle(&mut self) {
+        let (width, height) = self.grid.get_size();
+        self.scan_left = !self.scan_left;
+
+        for y in (0..height.saturating_sub(1)).rev() {
+            for i in 0..width {
+                let x = if self.scan_left { width - 1 - i } else { i };
+                if self.is_empty(x, y) {
+                    continue;
+                }
+
+                let target_x = if self.is_empty(x, y + 1) {
+                    Some(x)
+                } else {
+                    let mut sides = vec![];
+                    if x > 0 && self.is_empty(x - 1, y + 1) {
+                        sides.push(x - 1);
+                    }
+                    if x + 1 < width && self.is_empty(x + 1, y + 1) {
+                        sides.push(x + 1);
+                    }
+                    sides.choose(&mut self.rng).copied()
+                };
+
+                if let Some(target_x) = target_x {
+                    let grain = self.grid.get(x, y);
+                    self.grid.set(target_x, y + 1, grain);
+                    self.grid.set(x, y, Cell::default());
+                }
+            }
+        }
+    }
+
+    fn top_row_full(&self) -> bool {
+        let covered = (0..self.grid.width)
+            .filter(|x| !self.is_empty(*x, 0))
+            .count();
+        covered as f32 > self.grid.width as f32 * FULL_TOP_ROW
+    }
+
+    fn wander_spouts(&mut self) {
+        let width = self.grid.width;
+        for (x, _) in self.spouts.iter_mut() {
+            *x = match self.rng.gen_range(0..3) {
+                0 => x.saturating_sub(1),
+                1 => (*x + 1).min(width - 1),
+                _ => *x,
+            };
+        }
+    }
+
+    /// Drop new grains at the top row under random spouts
+    fn pour(&mut self) {
+        if self.spouts.is_empty() {
+            return;
+        }
+        let rate = self.options.spawn_rate.max(0.0);
+        let mut grains = rate as usize;
+        if self.rng.gen_bool(rate.fract() as f64) {
+            grains += 1;
+        }
+
+        for _ in 0..grains {
+            let (x, color) = self.spouts[self.rng.gen_range(0..self.spouts.len())];
+            if self.is_empty(x, 0) {
+                self.grid.set(
+                    x,
+                    0,
+                    Cell::new(GRAIN_CHAR, color, style::Attribute::Reset),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::buffer_to_string;
+
+    fn still_sand(width: u16, height: u16) -> Sand {
+        let options = SandOptionsBuilder::default()
+            .screen_size((width, height))
+            .spawn_rate(0.0)
+            .seed(Some(1))
+            .build()
+            .unwrap();
+        Sand::new(options)
+    }
+
+    fn grain() -> Cell {
+        Cell::new(GRAIN_CHAR, style::Color::Yellow, style::Attribute::Reset)
+    }
+
+    #[test]
+    fn grain_falls_to_the_bottom() {
+        let mut sand = still_sand(3, 4);
+        sand.grid.set(1, 0, grain());
+        for _ in 0..5 {
+            sand.settle();
+        }
+        assert_eq!(buffer_to_string(&sand.grid), "   \n   \n   \n █ ");
+    }
+
+    #[test]
+    fn grains_pile_up_in_slope() {
+        let mut sand = still_sand(3, 3);
+        for _ in 0..4 {
+            sand.grid.set(1, 0, grain());
+            for _ in 0..3 {
+                sand.settle();
+            }
+        }
+        assert_eq!(buffer_to_string(&sand.grid), "   \n █ \n███");
+    }
+
+    #[test]
+    fn resize_keeps_grains() {
+        let mut sand = still_sand(3, 3);
+        sand.grid.set(0, 2, grain());
+        sand.grid.set(2, 2, grain());
+        sand.get_diff();
+
+        sand.resize(2, 4);
+        assert_eq!(buffer_to_string(&sand.grid), "  \n  \n█ \n  ");
+        // screen is cleared after resize, so grain is drawn again
+        assert_eq!(sand.get_diff(), vec![(0, 2, grain())]);
+        assert!(sand.spouts.iter().all(|(x, _)| *x < 2));
+    }
+
+    #[test]
+    fn same_seed_same_frames() {
+        let options = SandOptionsBuilder::default()
+            .screen_size((20, 10))
+            .seed(Some(6))
+            .build()
+            .unwrap();
+        let mut first = Sand::new(options.clone());
+        let mut second = Sand::new(options);
+        for _ in 0..30 {
+            assert_eq!(first.get_diff(), second.get_diff());
+            first.update();
+            second.update();
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Oscilloscope showing Lissajous figures, two sine waves drive x and y
+//! of the beam, while phase between them slowly drifts the figure morphs.
+//! Drawn with braille characters, each cell holds 2x4 dots.
+use crate::braille::BrailleCanvas;
+use crate::buffer::{Buffer, Cell};
+use crate::common::TerminalEffect;
+use crossterm::{event, style};
+use derive_builder::Builder;
+use std::f32::consts::PI;
+
+/// Intensity below this value is not drawn
+const MIN_INTENSITY: f32 = 0.1;
+
+/// Number of curve samples per oscillator period
+const SAMPLES_PER_PERIOD: f32 = 120.0;
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct ScopeOptions {
+    screen_size: (u16, u16),
+    /// Frequencies of x and y oscillators, integer ratio gives closed figure
+    #[builder(default = "(3.0, 2.0)")]
+    freq_ratio: (f32, f32),
+    /// Phase shift between oscillators added each frame, radians
+    #[builder(default = "0.02")]
+    phase_speed: f32,
+    /// Part of the dot brightness kept to the next frame, 0.0 means no trails
+    #[builder(default = "0.7")]
+    persistence: f32,
+}
+
+pub struct Scope {
+    options: ScopeOptions,
+    buffer: Buffer,
+    /// Next frame is drawn here and swapped with `buffer`
+    back_buffer: Buffer,
+    /// Brightness of each braille dot, 2x4 dots per screen cell
+    dots: Vec<f32>,
+    phase: f32,
+    /// Multiplier of phase speed, changed by audio level in reactive mode
+    speed_factor: f32,
+}
+
+impl TerminalEffect for Scope {
+    fn name(&self) -> &'static str {
+        "scope"
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        self.back_buffer.clear();
+        fill_buffer(&self.dots, &mut self.back_buffer);
+
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
+        diff
+    }
+
+    fn update(&mut self) {
+        let persistence = self.options.persistence.clamp(0.0, 1.0);
+        for dot in self.dots.iter_mut() {
+            *dot *= persistence;
+        }
+        self.phase = (self.phase + self.options.phase_speed * self.speed_factor)
+            % (2.0 * PI);
+        self.draw_curve();
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.options.clone());
+    }
+
+    /// Left and right arrows change frequency of x oscillator, up and down
+    /// of y one, so other figures can be explored
+    fn on_key(&mut self, key: event::KeyEvent) {
+        let (x, y) = &mut self.options.freq_ratio;
+        match key.code {
+            event::KeyCode::Left => *x = (*x - 1.0).max(1.0),
+            event::KeyCode::Right => *x += 1.0,
+            event::KeyCode::Down => *y = (*y - 1.0).max(1.0),
+            event::KeyCode::Up => *y += 1.0,
+            _ => {}
+        }
+    }
+
+    /// Figure morphs faster on loud sound and almost stops in silence
+    fn set_intensity(&mut self, intensity: f32) {
+        self.speed_factor = 0.2 + 2.8 * intensity.clamp(0.0, 1.0);
+    }
+}
+
+impl Scope {
+    /// Printed by `tarts scope --help`
+    pub fn help() -> &'static str {
+        "scope: oscilloscope drawing Lissajous figures with braille dots
+
+Options:
+  --reactive        figure morphs faster on loud sound, needs `audio`
+                    feature
+
+Keys:
+  arrows            left and right change x frequency, up and down y one
+"
+    }
+
+    pub fn new(options: ScopeOptions) -> Self {
+        let (width, height) = (
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        let buffer = Buffer::new(width, height);
+        let dots = vec![0.0; width * 2 * height * 4];
+
+        let mut scope = Self {
+            options,
+            back_buffer: buffer.clone(),
+            buffer,
+            dots,
+            phase: 0.0,
+            speed_factor: 1.0,
+        };
+        scope.draw_curve();
+        scope
+    }
+
+    /// Trace one full period of the figure lighting dots along the way
+    fn draw_curve(&mut self) {
+        let mut canvas = BrailleCanvas::new(
+            self.options.screen_size.0 as usize,
+            self.options.screen_size.1 as usize,
+        );
+        let (dots_width, dots_height) = canvas.dots_size();
+        let points = lissajous_points(
+            self.options.freq_ratio,
+            self.phase,
+            dots_width,
+            dots_height,
+        );
+        for pair in points.windows(2) {
+            canvas.line(pair[0], pair[1]);
+        }
+
+        for y in 0..dots_height {
+            for x in 0..dots_width {
+                if canvas.is_set(x, y) {
+                    self.dots[y * dots_width + x] = 1.0;
+                }
+            }
+        }
+    }
+}
+
+/// Draw visible dots, brightest dot of the cell defines the color
+fn fill_buffer(dots: &[f32], buffer: &mut Buffer) {
+    let mut canvas = BrailleCanvas::new(buffer.width, buffer.height);
+    let (dots_width, _) = canvas.dots_size();
+    let mut intensity = vec![0.0_f32; buffer.width * buffer.height];
+
+    for (index, dot) in dots.iter().enumerate() {
+        if *dot >= MIN_INTENSITY {
+            let (x, y) = (index % dots_width, index / dots_width);
+            canvas.set(x, y);
+            let cell = (y / 4) * buffer.width + x / 2;
+            intensity[cell] = intensity[cell].max(*dot);
+        }
+    }
+
+    let width = buffer.width;
+    canvas.blit_with(buffer, |x, y| {
+        let intensity = intensity[y * width + x];
+        style::Color::Rgb {
+            r: 0,
+            g: (55.0 + 200.0 * intensity) as u8,
+            b: (40.0 * intensity) as u8,
+        }
+    });
+}
+
+/// Sample figure on the dots grid, from t = 0 to t = 2π
+pub fn lissajous_points(
+    freq_ratio: (f32, f32),
+    phase: f32,
+    dots_width: usize,
+    dots_height: usize,
+) -> Vec<(usize, usize)> {
+    let (a, b) = freq_ratio;
+    let samples = (SAMPLES_PER_PERIOD * a.abs().max(b.abs()).max(1.0)) as usize;
+    let max_x = dots_width.saturating_sub(1) as f32;
+    let max_y = dots_height.saturating_sub(1) as f32;
+
+    (0..=samples)
+        .map(|i| {
+            let t = 2.0 * PI * i as f32 / samples as f32;
+            let x = ((a * t + phase).sin() + 1.0) / 2.0;
+            let y = ((b * t).sin() + 1.0) / 2.0;
+            ((x * max_x).round() as usize, (y * max_y).round() as usize)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_figure_for_integer_ratio() {
+        for ratio in [(1.0, 1.0), (3.0, 2.0), (5.0, 4.0)] {
+            let points = lissajous_points(ratio, 0.3, 160, 160);
+            assert_eq!(points.first(), points.last());
+        }
+
+        let points = lissajous_points((1.37, 1.0), 0.3, 160, 160);
+        assert_ne!(points.first(), points.last());
+    }
+
+    #[test]
+    fn curve_is_drawn_with_braille() {
+        let options = ScopeOptionsBuilder::default()
+            .screen_size((20, 10))
+            .build()
+            .unwrap();
+        let mut scope = Scope::new(options);
+        let diff = scope.get_diff();
+        assert!(!diff.is_empty());
+        assert!(diff
+            .iter()
+            .all(|(_, _, cell)| ('\u{2801}'..='\u{28FF}').contains(&cell.symbol)));
+    }
+
+    #[test]
+    fn arrows_change_frequencies() {
+        let options = ScopeOptionsBuilder::default()
+            .screen_size((20, 10))
+            .build()
+            .unwrap();
+        let mut scope = Scope::new(options);
+        let arrow = |code| event::KeyEvent::new(code, event::KeyModifiers::NONE);
+        scope.on_key(arrow(event::KeyCode::Right));
+        scope.on_key(arrow(event::KeyCode::Down));
+        scope.on_key(arrow(event::KeyCode::Down));
+        assert_eq!(scope.options.freq_ratio, (4.0, 1.0));
+    }
+
+    #[test]
+    fn intensity_scales_phase_speed() {
+        let options = ScopeOptionsBuilder::default()
+            .screen_size((20, 10))
+            .phase_speed(0.1)
+            .build()
+            .unwrap();
+        let mut quiet = Scope::new(options.clone());
+        let mut loud = Scope::new(options);
+        quiet.set_intensity(0.0);
+        loud.set_intensity(1.0);
+        quiet.update();
+        loud.update();
+        assert!(loud.phase > quiet.phase * 10.0);
+    }
+
+    #[test]
+    fn trails_fade_out() {
+        let options = ScopeOptionsBuilder::default()
+            .screen_size((20, 10))
+            .persistence(0.5)
+            .phase_speed(0.5)
+            .build()
+            .unwrap();
+        let mut scope = Scope::new(options);
+        assert!(scope.dots.iter().all(|d| *d == 0.0 || *d == 1.0));
+
+        // previous figure is still visible but dimmer
+        scope.update();
+        assert!(scope.dots.contains(&0.5));
+
+        // and disappears after a few frames
+        scope.options.phase_speed = 0.0;
+        for _ in 0..5 {
+            scope.update();
+        }
+        assert!(scope.dots.iter().all(|d| *d == 1.0 || *d < MIN_INTENSITY));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod effect;
+pub use effect::{Scope, ScopeOptions, ScopeOptionsBuilder};
@@ -0,0 +1,375 @@
+//! WaTor, predator-prey simulation on toroidal ocean. Each chronon every
+//! creature makes one move in random order:
+//!
+//! Fish:
+//!     Swims to random free neighbor cell, after `fish_breed` chronons
+//!     leaves a new fish at the old place.
+//! Shark:
+//!     Eats fish from random neighbor cell, otherwise swims like a fish.
+//!     Breeds after `shark_breed` chronons and dies if it haven't eaten
+//!     for `shark_starve` chronons.
+use crate::buffer::{Buffer, Cell};
+use crate::common::{make_rng, EffectRng, TerminalEffect};
+use crossterm::style;
+use derive_builder::Builder;
+use rand::{seq::SliceRandom, Rng};
+use std::collections::HashMap;
+
+const FISH_CHAR: char = '•';
+const SHARK_CHAR: char = '▲';
+
+#[derive(Builder, Default, Debug, Clone)]
+#[builder(public, setter(into))]
+pub struct WatorOptions {
+    screen_size: (u16, u16),
+    /// Chronons fish needs to survive before it breeds
+    #[builder(default = "3")]
+    fish_breed: u32,
+    /// Chronons shark needs to survive before it breeds
+    #[builder(default = "10")]
+    shark_breed: u32,
+    /// Chronons shark survives without eating
+    #[builder(default = "3")]
+    shark_starve: u32,
+    #[builder(default = "600")]
+    initial_fish: u32,
+    #[builder(default = "60")]
+    initial_sharks: u32,
+    /// Seed for random generator, non-deterministic if not set
+    #[builder(default)]
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Species {
+    Fish,
+    Shark,
+}
+
+#[derive(Debug, Clone)]
+pub struct Creature {
+    pub species: Species,
+    /// Chronons since creature was born or bred last time
+    pub age: u32,
+    /// Chronons since shark has eaten last time
+    pub hunger: u32,
+    /// Chronon when creature moved last time, so it moves once per chronon
+    moved_at: u64,
+}
+
+pub struct Wator {
+    options: WatorOptions,
+    buffer: Buffer,
+    /// Next frame is drawn here and swapped with `buffer`
+    back_buffer: Buffer,
+    creatures: HashMap<(usize, usize), Creature>,
+    rng: EffectRng,
+    chronon: u64,
+}
+
+impl Creature {
+    pub fn new(species: Species) -> Self {
+        Self {
+            species,
+            age: 0,
+            hunger: 0,
+            moved_at: 0,
+        }
+    }
+}
+
+impl TerminalEffect for Wator {
+    fn name(&self) -> &'static str {
+        "wator"
+    }
+
+    fn get_diff(&mut self) -> Vec<(usize, usize, Cell)> {
+        self.back_buffer.clear();
+        Self::fill_buffer(&self.creatures, &mut self.back_buffer);
+
+        let diff = self.buffer.diff(&self.back_buffer);
+        std::mem::swap(&mut self.buffer, &mut self.back_buffer);
+        diff
+    }
+
+    fn update(&mut self) {
+        self.chronon += 1;
+
+        // hashmap order differs between runs, sort to keep seeded runs same
+        let mut positions: Vec<(usize, usize)> =
+            self.creatures.keys().copied().collect();
+        positions.sort_unstable();
+        positions.shuffle(&mut self.rng);
+
+        for position in positions {
+            self.step(position);
+        }
+
+        // one of species is extinct, release some more
+        if self.count(Species::Fish) == 0 {
+            self.populate(Species::Fish, self.options.initial_fish);
+        }
+        if self.count(Species::Shark) == 0 {
+            self.populate(Species::Shark, self.options.initial_sharks);
+        }
+    }
+
+    fn update_size(&mut self, width: u16, height: u16) {
+        self.options.screen_size = (width, height);
+    }
+
+    fn reset(&mut self) {
+        let new_effect = Self::with_rng(self.options.clone(), self.rng.clone());
+        *self = new_effect;
+    }
+
+    /// Creatures inside new screen keep living, the rest are gone
+    fn resize(&mut self, width: u16, height: u16) {
+        self.update_size(width, height);
+        let (width, height) = (width as usize, height as usize);
+        self.creatures.retain(|&(x, y), _| x < width && y < height);
+        self.buffer = Buffer::new(width, height);
+        self.back_buffer = self.buffer.clone();
+    }
+}
+
+impl Wator {
+    /// Printed by `tarts wator --help`
+    pub fn help() -> &'static str {
+        "wator: predator-prey simulation of fish and sharks on toroidal ocean
+
+Options:
+  --seed <n>        seed random generator
+"
+    }
+
+    pub fn new(options: WatorOptions) -> Self {
+        let rng = make_rng(options.seed);
+        Self::with_rng(options, rng)
+    }
+
+    /// Create effect with provided random generator
+    pub fn with_rng(options: WatorOptions, rng: EffectRng) -> Self {
+        let buffer = Buffer::new(
+            options.screen_size.0 as usize,
+            options.screen_size.1 as usize,
+        );
+        let (initial_fish, initial_sharks) =
+            (options.initial_fish, options.initial_sharks);
+
+        let mut wator = Self {
+            options,
+            back_buffer: buffer.clone(),
+            buffer,
+            creatures: HashMap::new(),
+            rng,
+            chronon: 0,
+        };
+        wator.populate(Species::Fish, initial_fish);
+        wator.populate(Species::Shark, initial_sharks);
+        wator
+    }
+
+    /// Number of creatures of given species
+    pub fn count(&self, species: Species) -> usize {
+        self.creatures
+            .values()
+            .filter(|creature| creature.species == species)
+            .count()
+    }
+
+    /// Put creatures to random places, occupied places are skipped
+    fn populate(&mut self, species: Species, number: u32) {
+        for _ in 0..number {
+            let x = self.rng.gen_range(0..self.buffer.width);
+            let y = self.rng.gen_range(0..self.buffer.height);
+            self.creatures
+                .entry((x, y))
+                .or_insert_with(|| Creature::new(species));
+        }
+    }
+
+    /// Four neighbors of the cell, edges are wrapped around
+    fn neighbors(&self, (x, y): (usize, usize)) -> [(usize, usize); 4] {
+        let (width, height) = (self.buffer.width, self.buffer.height);
+        [
+            (x, (y + height - 1) % height),
+            ((x + 1) % width, y),
+            (x, (y + 1) % height),
+            ((x + width - 1) % width, y),
+        ]
+    }
+
+    /// Random neighbor cell matching the predicate
+    fn pick_neighbor<F>(
+        &mut self,
+        position: (usize, usize),
+        predicate: F,
+    ) -> Option<(usize, usize)>
+    where
+        F: Fn(Option<&Creature>) -> bool,
+    {
+        let candidates: Vec<(usize, usize)> = self
+            .neighbors(position)
+            .into_iter()
+            .filter(|neighbor| {
+                *neighbor != position && predicate(self.creatures.get(neighbor))
+            })
+            .collect();
+        candidates.choose(&mut self.rng).copied()
+    }
+
+    /// Move creature at the position by the rules of its species
+    fn step(&mut self, position: (usize, usize)) {
+        // creature could be eaten or it has already moved there
+        let Some(creature) = self.creatures.get(&position) else {
+            return;
+        };
+        if creature.moved_at == self.chronon {
+            return;
+        }
+        let species = creature.species;
+
+        let mut target = None;
+        if species == Species::Shark {
+            target = self.pick_neighbor(position, |neighbor| {
+                matches!(neighbor, Some(c) if c.species == Species::Fish)
+            });
+        }
+        let ate = target.is_some();
+        if target.is_none() {
+            target = self.pick_neighbor(position, |neighbor| neighbor.is_none());
+        }
+
+        let mut creature = self.creatures.remove(&position).unwrap();
+        creature.moved_at = self.chronon;
+        creature.age += 1;
+        if species == Species::Shark {
+            creature.hunger = if ate { 0 } else { creature.hunger + 1 };
+            if creature.hunger >= self.options.shark_starve {
+                return;
+            }
+        }
+
+        let breed = match species {
+            Species::Fish => self.options.fish_breed,
+            Species::Shark => self.options.shark_breed,
+        };
+        match target {
+            Some(target) => {
+                if creature.age >= breed {
+                    creature.age = 0;
+                    let mut child = Creature::new(species);
+                    child.moved_at = self.chronon;
+                    self.creatures.insert(position, child);
+                }
+                self.creatures.insert(target, creature);
+            }
+            None => {
+                self.creatures.insert(position, creature);
+            }
+        }
+    }
+
+    /// Fish are drawn green and sharks red
+    pub fn fill_buffer(
+        creatures: &HashMap<(usize, usize), Creature>,
+        buffer: &mut Buffer,
+    ) {
+        for ((x, y), creature) in creatures.iter() {
+            let cell = match creature.species {
+                Species::Fish => Cell::new(
+                    FISH_CHAR,
+                    style::Color::Green,
+                    style::Attribute::Reset,
+                ),
+                Species::Shark => {
+                    Cell::new(SHARK_CHAR, style::Color::Red, style::Attribute::Bold)
+                }
+            };
+            if *x < buffer.width && *y < buffer.height {
+                buffer.set(*x, *y, cell);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_ocean() -> Wator {
+        let options = WatorOptionsBuilder::default()
+            .screen_size((10, 10))
+            .initial_fish(0_u32)
+            .initial_sharks(0_u32)
+            .seed(Some(1))
+            .build()
+            .unwrap();
+        Wator::new(options)
+    }
+
+    #[test]
+    fn shark_eats_neighbor_fish() {
+        let mut wator = empty_ocean();
+        wator
+            .creatures
+            .insert((5, 5), Creature::new(Species::Shark));
+        wator.creatures.insert((5, 6), Creature::new(Species::Fish));
+        wator.chronon = 1;
+        wator.step((5, 5));
+
+        assert_eq!(wator.count(Species::Fish), 0);
+        assert_eq!(
+            wator.creatures.get(&(5, 6)).unwrap().species,
+            Species::Shark
+        );
+        assert_eq!(wator.creatures.get(&(5, 6)).unwrap().hunger, 0);
+    }
+
+    #[test]
+    fn shark_starves() {
+        let mut wator = empty_ocean();
+        let mut shark = Creature::new(Species::Shark);
+        shark.hunger = 2;
+        wator.creatures.insert((0, 0), shark);
+        wator.chronon = 1;
+        wator.step((0, 0));
+        assert_eq!(wator.count(Species::Shark), 0);
+    }
+
+    #[test]
+    fn fish_breeds_and_wraps_around() {
+        let mut wator = empty_ocean();
+        let mut fish = Creature::new(Species::Fish);
+        fish.age = 2;
+        wator.creatures.insert((0, 0), fish);
+        wator.chronon = 1;
+        wator.step((0, 0));
+
+        // child stays in place, parent swims to wrapped neighbor
+        assert_eq!(wator.count(Species::Fish), 2);
+        assert!(wator.creatures.contains_key(&(0, 0)));
+        assert!([(0, 9), (1, 0), (0, 1), (9, 0)]
+            .iter()
+            .any(|position| wator.creatures.contains_key(position)));
+    }
+
+    #[test]
+    fn same_seed_same_frames() {
+        let options = WatorOptionsBuilder::default()
+            .screen_size((30, 15))
+            .initial_fish(100_u32)
+            .initial_sharks(10_u32)
+            .seed(Some(3))
+            .build()
+            .unwrap();
+        let mut first = Wator::new(options.clone());
+        let mut second = Wator::new(options);
+        for _ in 0..20 {
+            assert_eq!(first.get_diff(), second.get_diff());
+            first.update();
+            second.update();
+        }
+    }
+}